@@ -0,0 +1,55 @@
+use jsave::{Bincode, Json, Mutex};
+use serde::{Deserialize, Serialize};
+use std::{
+    env, fs,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+const ITEMS: u32 = 200_000;
+const RUNS: u32 = 10;
+
+#[derive(Serialize, Deserialize)]
+struct Item {
+    id: u32,
+    name: String,
+    values: Vec<u32>,
+}
+
+fn data() -> Vec<Item> {
+    (0..ITEMS)
+        .map(|id| Item {
+            id,
+            name: format!("item {}", id),
+            values: (0..8).map(|i| id.wrapping_mul(i)).collect(),
+        })
+        .collect()
+}
+
+fn bench_save<Fmt: jsave::Format>(name: &str, path: &Path) {
+    let lock: Mutex<Vec<Item>, Fmt> = Mutex::init_with(data(), path).unwrap();
+    let mut total = Duration::ZERO;
+
+    for _ in 0..RUNS {
+        lock.mark_dirty();
+        let start = Instant::now();
+        lock.save().unwrap();
+        total += start.elapsed();
+    }
+
+    let size = fs::metadata(path).unwrap().len();
+    println!(
+        "{:8} save: {:?} per run, {} bytes",
+        name,
+        total / RUNS,
+        size
+    );
+    let _ = fs::remove_file(path);
+}
+
+fn main() {
+    let dir = env::temp_dir();
+    println!("saving {} items", ITEMS);
+    bench_save::<Json>("JSON", &dir.join("jsave-bench.json"));
+    bench_save::<Bincode>("bincode", &dir.join("jsave-bench.bin"));
+}
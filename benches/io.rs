@@ -0,0 +1,60 @@
+use jsave::Mutex;
+use std::{
+    collections::BTreeMap,
+    env,
+    fs::{self, File},
+    time::{Duration, Instant},
+};
+
+const RUNS: u32 = 5;
+
+type Data = Vec<BTreeMap<String, Vec<Vec<u32>>>>;
+
+fn data() -> Data {
+    (0..2_000)
+        .map(|i| {
+            (0..10)
+                .map(|j| (format!("key {}", j), vec![vec![i, j, i * j]; 8]))
+                .collect()
+        })
+        .collect()
+}
+
+fn time<F: FnMut()>(mut f: F) -> Duration {
+    let start = Instant::now();
+
+    for _ in 0..RUNS {
+        f();
+    }
+
+    start.elapsed() / RUNS
+}
+
+fn main() {
+    let path = env::temp_dir().join("jsave-bench-io.json");
+    let lock: Mutex<Data> = Mutex::init_with(data(), &path).unwrap();
+    println!("file size: {} bytes", fs::metadata(&path).unwrap().len());
+
+    // The baselines read and write the file without a buffer, as jsave did before
+    let unbuffered = time(|| {
+        serde_json::to_writer(File::create(&path).unwrap(), &*lock.lock()).unwrap();
+    });
+
+    let buffered = time(|| {
+        lock.mark_dirty();
+        lock.save().unwrap();
+    });
+
+    println!("save: {:?} unbuffered, {:?} buffered", unbuffered, buffered);
+
+    let unbuffered = time(|| {
+        serde_json::from_reader::<_, Data>(File::open(&path).unwrap()).unwrap();
+    });
+
+    let buffered = time(|| {
+        jsave::from_path::<Data, _>(&path).unwrap();
+    });
+
+    println!("load: {:?} unbuffered, {:?} buffered", unbuffered, buffered);
+    let _ = fs::remove_file(&path);
+}
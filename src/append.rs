@@ -0,0 +1,201 @@
+use crate::{error::IoContext, Error, IoOp, Options};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::Hasher,
+    io::{ErrorKind, Write},
+    path::Path,
+};
+
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+
+pub(crate) enum Append {
+    Appended(usize),
+    // The whole file has to be rewritten, after which `rewritten` records its lines if they can be appended to
+    Rewrite(Option<Lines>),
+}
+
+// What the JSON Lines file holds after the last save of the lock: the number of items, a hash of their lines, and the
+// length of the file
+#[derive(Clone, Copy)]
+pub(crate) struct Lines {
+    items: usize,
+    hash: u64,
+    len: u64,
+}
+
+// Only `save_append` records the lines. Every other save or load of the file forgets them, so the next append
+// rewrites the whole file
+pub(crate) struct PersistedLines(Mutex<Option<Lines>>);
+
+impl PersistedLines {
+    #[inline]
+    pub(crate) const fn new() -> Self {
+        Self(parking_lot::const_mutex(None))
+    }
+
+    #[inline]
+    pub(crate) fn forget(&self) {
+        *self.0.lock() = None;
+    }
+}
+
+// Copies of the options save elsewhere, like `save_as` and `clone_to`
+impl Clone for PersistedLines {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+}
+
+// Appends the items that are not in the JSON Lines file yet. The items already in the file must not have changed since
+// the last `save_append`, which is checked by hashing their lines, so every item is still serialized but only the new
+// ones are written
+pub(crate) fn append_data_to_path<T>(
+    data: &T,
+    path: &Path,
+    options: &Options,
+    sync: bool,
+) -> Result<Append, Error>
+where
+    T: ?Sized,
+    for<'a> &'a T: IntoIterator,
+    for<'a> <&'a T as IntoIterator>::Item: Serialize,
+{
+    append_items(data, path, options, sync).map_err(|err| err.saving(path))
+}
+
+// Records the lines written by a full save, so that the next `save_append` can append to them
+#[inline]
+pub(crate) fn rewritten(options: &Options, lines: Option<Lines>, len: usize) {
+    if let Some(lines) = lines {
+        *options.persisted_lines.0.lock() = Some(Lines {
+            len: len as u64,
+            ..lines
+        });
+    }
+}
+
+fn append_items<T>(data: &T, path: &Path, options: &Options, sync: bool) -> Result<Append, Error>
+where
+    T: ?Sized,
+    for<'a> &'a T: IntoIterator,
+    for<'a> <&'a T as IntoIterator>::Item: Serialize,
+{
+    if options.memory.is_some() {
+        return Ok(Append::Rewrite(None));
+    }
+
+    #[cfg(feature = "compression")]
+    if options.compression.is_some() {
+        return Ok(Append::Rewrite(None));
+    }
+
+    #[cfg(feature = "encryption")]
+    if options.cipher.is_some() {
+        return Ok(Append::Rewrite(None));
+    }
+
+    let persisted = *options.persisted_lines.0.lock();
+    let persisted_items = persisted.map_or(0, |lines| lines.items);
+
+    let mut hasher = DefaultHasher::new();
+    let mut persisted_hash = None;
+    let mut line = Vec::new();
+    let mut buf = Vec::new();
+    let mut items = 0;
+
+    for item in data {
+        if items == persisted_items {
+            persisted_hash = Some(hasher.finish());
+        }
+
+        line.clear();
+
+        if options.sorts_keys() {
+            serde_json::to_writer(&mut line, &crate::sort_keys(serde_json::to_value(item)?))?;
+        } else {
+            serde_json::to_writer(&mut line, &item)?;
+        }
+
+        line.push(b'\n');
+        hasher.write(&line);
+
+        if items >= persisted_items {
+            buf.extend_from_slice(&line);
+        }
+
+        items += 1;
+    }
+
+    if items == persisted_items {
+        persisted_hash = Some(hasher.finish());
+    }
+
+    let lines = Lines {
+        items,
+        hash: hasher.finish(),
+        len: 0,
+    };
+
+    let persisted = match persisted {
+        Some(persisted) if persisted_hash == Some(persisted.hash) => persisted,
+        _ => return Ok(Append::Rewrite(Some(lines))),
+    };
+
+    // The file may have been written by something else than this lock
+    let len = match fs::metadata(path) {
+        Ok(metadata) => metadata.len(),
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Append::Rewrite(Some(lines))),
+        Err(err) => {
+            return Err(Error::Io {
+                op: IoOp::Read,
+                source: err,
+            })
+        }
+    };
+
+    if len != persisted.len {
+        return Ok(Append::Rewrite(Some(lines)));
+    }
+
+    #[cfg(feature = "checksum")]
+    let checksum = if options.checksum {
+        match crate::checksum::read_checksum(path).context(IoOp::Read)? {
+            Some(checksum) => Some(checksum),
+            None => return Ok(Append::Rewrite(Some(lines))),
+        }
+    } else {
+        None
+    };
+
+    let mut open_options = options.open_options();
+    open_options.append(true).create(true);
+
+    #[cfg(unix)]
+    if let Some(mode) = options.mode {
+        open_options.mode(mode);
+    }
+
+    let mut file = open_options.open(path).context(IoOp::Open)?;
+    file.write_all(&buf).context(IoOp::Write)?;
+
+    if sync {
+        file.sync_all().context(IoOp::Sync)?;
+    }
+
+    // Appending is not atomic, a crash in between leaves the checksum of the old content
+    #[cfg(feature = "checksum")]
+    if let Some(checksum) = checksum {
+        let mut hasher = crc32fast::Hasher::new_with_initial(checksum);
+        hasher.update(&buf);
+        crate::checksum::write_checksums(path, &[hasher.finalize()], sync).context(IoOp::Write)?;
+    }
+
+    options.saved(path, buf.len());
+    rewritten(options, Some(lines), len as usize + buf.len());
+    Ok(Append::Appended(buf.len()))
+}
@@ -0,0 +1,85 @@
+use std::{
+    sync::mpsc::{self, RecvTimeoutError, Sender},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+#[cfg(feature = "tokio")]
+use std::future::Future;
+
+/// Handle of a background autosave thread. The thread is stopped when the handle is dropped, so it must be kept alive
+/// for as long as the data should be saved
+#[must_use = "the autosave thread is stopped when the handle is dropped"]
+pub struct AutosaveHandle {
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl AutosaveHandle {
+    pub(crate) fn spawn<F>(interval: Duration, mut f: F) -> Self
+    where
+        F: FnMut() + Send + 'static,
+    {
+        let (stop, stop_rx) = mpsc::channel();
+
+        let thread = thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stop_rx.recv_timeout(interval) {
+                f();
+            }
+        });
+
+        Self {
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for AutosaveHandle {
+    fn drop(&mut self) {
+        // Dropping the sender wakes the thread up immediately
+        self.stop.take();
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Handle of an autosave task on the tokio runtime. The task is aborted when the handle is dropped
+#[cfg(feature = "tokio")]
+#[must_use = "the autosave task is aborted when the handle is dropped"]
+pub struct AsyncAutosaveHandle {
+    task: tokio::task::JoinHandle<()>,
+}
+
+#[cfg(feature = "tokio")]
+impl AsyncAutosaveHandle {
+    pub(crate) fn spawn<F, Fut>(interval: Duration, mut f: F) -> Self
+    where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send,
+    {
+        let task = tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(interval);
+            ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+            // The first tick completes immediately
+            ticks.tick().await;
+
+            loop {
+                ticks.tick().await;
+                f().await;
+            }
+        });
+
+        Self { task }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Drop for AsyncAutosaveHandle {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
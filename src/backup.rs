@@ -0,0 +1,86 @@
+use crate::{Error, Format, Options};
+use serde::de::DeserializeOwned;
+use std::{
+    fs,
+    io::{ErrorKind, Result as IoResult},
+    path::{Path, PathBuf},
+};
+
+// Shifts `<path>.1` .. `<path>.<count - 1>` up by one, then links the current file to `<path>.1`. The current file is
+// only replaced afterwards by a `rename`, so a crash at any point leaves both it and its latest backup on disk
+pub(crate) fn rotate_backups(path: &Path, options: &Options) -> IoResult<()> {
+    for idx in (1..options.backups).rev() {
+        rename_if_exists(&backup_path(path, idx), &backup_path(path, idx + 1))?;
+
+        #[cfg(feature = "checksum")]
+        if options.checksum {
+            rename_if_exists(
+                &crate::checksum::checksum_path(&backup_path(path, idx)),
+                &crate::checksum::checksum_path(&backup_path(path, idx + 1)),
+            )?;
+        }
+    }
+
+    link_or_copy(path, &backup_path(path, 1))?;
+
+    #[cfg(feature = "checksum")]
+    if options.checksum {
+        link_or_copy(
+            &crate::checksum::checksum_path(path),
+            &crate::checksum::checksum_path(&backup_path(path, 1)),
+        )?;
+    }
+
+    Ok(())
+}
+
+// Falls back to the backups from the newest to the oldest if the file exists but can't be loaded, and returns the
+// error of the file if none of them can be loaded either
+pub(crate) fn load_with_recovery<F, T>(path: &Path, options: &Options) -> Result<T, Error>
+where
+    F: Format,
+    T: DeserializeOwned,
+{
+    let err = match crate::load_data_from_path::<F, T>(path, options) {
+        Err(err) if !err.is_not_found() => err,
+        res => return res,
+    };
+
+    // Backups are rotated in order, so the first missing one is the end
+    for idx in 1.. {
+        match crate::load_data_from_path::<F, T>(&backup_path(path, idx), options) {
+            Ok(data) => return Ok(data),
+            Err(err) if err.is_not_found() => break,
+            Err(_) => {}
+        }
+    }
+
+    Err(err)
+}
+
+fn rename_if_exists(from: &Path, to: &Path) -> IoResult<()> {
+    match fs::rename(from, to) {
+        Err(err) if err.kind() != ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+fn link_or_copy(from: &Path, to: &Path) -> IoResult<()> {
+    if let Err(err) = fs::remove_file(to) {
+        if err.kind() != ErrorKind::NotFound {
+            return Err(err);
+        }
+    }
+
+    // Hard links are not supported by every filesystem
+    let res = fs::hard_link(from, to).or_else(|_| fs::copy(from, to).map(|_| ()));
+
+    match res {
+        Err(err) if err.kind() != ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+fn backup_path(path: &Path, idx: usize) -> PathBuf {
+    crate::append_to_path(path, &format!(".{}", idx))
+}
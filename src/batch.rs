@@ -0,0 +1,108 @@
+use crate::{Error, Options};
+use std::{
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Implemented by all lock types, so that they can be saved together with `save_all`
+pub trait Saveable: Sealed {}
+
+impl<S: Sealed + ?Sized> Saveable for S {}
+
+/// Writes every lock to its temporary file first, and only replaces the real files once all of them are written. A
+/// failure while serializing leaves all files untouched. Locks that have not been modified are skipped. All locks are
+/// held until their files are replaced
+pub fn save_all(locks: &[&dyn Saveable]) -> Result<(), Error> {
+    // Taking the locks in address order keeps concurrent batches from deadlocking, and a lock listed twice from
+    // deadlocking with itself
+    let mut locks = locks.to_vec();
+    locks.sort_by_key(|lock| address(*lock));
+    locks.dedup_by_key(|lock| address(*lock));
+
+    let mut pending = Vec::with_capacity(locks.len());
+
+    for lock in locks {
+        match lock.write_tmp_file() {
+            Ok(Some(save)) => pending.push(save),
+            Ok(None) => {}
+            Err(err) => {
+                pending.into_iter().for_each(PendingSave::discard);
+                return Err(err);
+            }
+        }
+    }
+
+    let mut pending = pending.into_iter();
+
+    while let Some(save) = pending.next() {
+        if let Err(err) = save.commit() {
+            pending.for_each(PendingSave::discard);
+            return Err(err);
+        }
+    }
+
+    Ok(())
+}
+
+#[inline]
+fn address(lock: &dyn Saveable) -> usize {
+    lock as *const dyn Saveable as *const u8 as usize
+}
+
+pub trait Sealed {
+    fn write_tmp_file(&self) -> Result<Option<PendingSave<'_>>, Error>;
+}
+
+// Any guard of the lock being saved
+pub(crate) trait Held {}
+
+impl<T> Held for T {}
+
+// Holds the lock until the temporary file is committed, as another save would write to the same temporary file
+pub struct PendingSave<'a> {
+    path: &'a Path,
+    options: &'a Options,
+    len: usize,
+    dirty: Option<&'a AtomicBool>,
+    _guard: Box<dyn Held + 'a>,
+}
+
+impl<'a> PendingSave<'a> {
+    pub(crate) fn new(
+        path: &'a Path,
+        options: &'a Options,
+        len: usize,
+        dirty: Option<&'a AtomicBool>,
+        guard: Box<dyn Held + 'a>,
+    ) -> Self {
+        Self {
+            path,
+            options,
+            len,
+            dirty,
+            _guard: guard,
+        }
+    }
+
+    fn commit(self) -> Result<(), Error> {
+        match crate::commit_tmp_file(self.path, self.options) {
+            Ok(()) => {
+                self.options.saved(self.path, self.len);
+                Ok(())
+            }
+            Err(err) => {
+                self.discard();
+                Err(err)
+            }
+        }
+    }
+
+    // The data was not saved after all, so it has to be marked as modified again
+    fn discard(self) {
+        crate::remove_tmp_file(self.path, self.options);
+
+        if let Some(dirty) = self.dirty {
+            dirty.store(true, Ordering::Relaxed);
+        }
+    }
+}
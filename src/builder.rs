@@ -0,0 +1,178 @@
+use crate::{Error, Format, Json, Options};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{fs::OpenOptions, marker::PhantomData, path::PathBuf, time::Duration};
+
+/// Collects the options of a lock before its file is opened, so that they also apply to loading it. The type of lock
+/// is picked by `open`, e.g. `let db: Mutex<Data> = Builder::new(path).pretty(true).open()?`
+pub struct Builder<T, Fmt = Json> {
+    pub(crate) file_path: PathBuf,
+    pub(crate) options: Options,
+    pub(crate) autosave: bool,
+    data: PhantomData<T>,
+    format: PhantomData<Fmt>,
+}
+
+// The format defaults to JSON and is changed with `format`, as the default can't be inferred otherwise
+impl<T> Builder<T> {
+    #[inline]
+    pub fn new<P: Into<PathBuf>>(file_path: P) -> Self {
+        Self {
+            file_path: file_path.into(),
+            options: Options::default(),
+            autosave: false,
+            data: PhantomData,
+            format: PhantomData,
+        }
+    }
+}
+
+impl<T, Fmt> Builder<T, Fmt> {
+    #[inline]
+    pub fn path<P: Into<PathBuf>>(mut self, file_path: P) -> Self {
+        self.file_path = file_path.into();
+        self
+    }
+
+    #[inline]
+    pub fn format<F: Format>(self) -> Builder<T, F> {
+        Builder {
+            file_path: self.file_path,
+            options: self.options,
+            autosave: self.autosave,
+            data: PhantomData,
+            format: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub fn pretty(mut self, pretty: bool) -> Self {
+        self.options.pretty = pretty;
+        self
+    }
+
+    #[inline]
+    pub fn indent<S: Into<String>>(mut self, indent: S) -> Self {
+        self.options.pretty = true;
+        self.options.indent = Some(indent.into());
+        self
+    }
+
+    #[inline]
+    pub fn mode(mut self, mode: u32) -> Self {
+        self.options.mode = Some(mode);
+        self
+    }
+
+    #[inline]
+    pub fn backups(mut self, count: usize) -> Self {
+        self.options.backups = count;
+        self
+    }
+
+    #[inline]
+    pub fn retries(mut self, count: usize, backoff: Duration) -> Self {
+        self.options.retries = count;
+        self.options.retry_backoff = backoff;
+        self
+    }
+
+    #[inline]
+    pub fn open_options(mut self, open_options: OpenOptions) -> Self {
+        self.options.open_options = Some(open_options);
+        self
+    }
+
+    #[inline]
+    pub fn create_dirs(mut self, create_dirs: bool) -> Self {
+        self.options.create_dirs = create_dirs;
+        self
+    }
+
+    #[inline]
+    pub fn sorted_keys(mut self, sorted: bool) -> Self {
+        self.options.sort_keys = sorted;
+        self
+    }
+
+    #[inline]
+    pub fn trailing_newline(mut self, trailing_newline: bool) -> Self {
+        self.options.trailing_newline = trailing_newline;
+        self
+    }
+
+    #[inline]
+    pub fn canonical(mut self, canonical: bool) -> Self {
+        self.options.canonical = canonical;
+        self
+    }
+
+    #[inline]
+    pub fn temp_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.options.temp_dir = Some(dir.into());
+        self
+    }
+
+    /// Ignored by `ReentrantMutex`, which has no autosave
+    #[inline]
+    pub fn autosave(mut self, autosave: bool) -> Self {
+        self.autosave = autosave;
+        self
+    }
+
+    #[cfg(feature = "checksum")]
+    #[inline]
+    pub fn checksum(mut self, checksum: bool) -> Self {
+        self.options.checksum = checksum;
+        self
+    }
+
+    #[cfg(feature = "compression")]
+    #[inline]
+    pub fn compression(mut self, level: u32) -> Self {
+        self.options.compression = Some(level);
+        self
+    }
+
+    #[cfg(feature = "encryption")]
+    #[inline]
+    pub fn cipher(mut self, key: [u8; 32]) -> Self {
+        self.options.cipher = Some(key);
+        self
+    }
+}
+
+impl<T, Fmt> Builder<T, Fmt>
+where
+    T: Serialize + DeserializeOwned,
+    Fmt: Format,
+{
+    #[inline]
+    pub fn open<L: FromBuilder<T, Fmt>>(self) -> Result<L, Error> {
+        let data = crate::load_data_from_path::<Fmt, _>(&self.file_path, &self.options)?;
+        self.open_with(data)
+    }
+
+    #[inline]
+    pub fn open_or_default<L: FromBuilder<T, Fmt>>(self) -> Result<L, Error>
+    where
+        T: Default,
+    {
+        let data = match crate::load_data_from_path::<Fmt, _>(&self.file_path, &self.options) {
+            Ok(data) => data,
+            Err(err) if err.is_missing_file() => T::default(),
+            Err(err) => return Err(err),
+        };
+
+        self.open_with(data)
+    }
+
+    fn open_with<L: FromBuilder<T, Fmt>>(self, data: T) -> Result<L, Error> {
+        crate::save_data_to_path::<Fmt, _>(&data, &self.file_path, &self.options, false)?;
+        Ok(L::from_builder(self, data))
+    }
+}
+
+/// The lock types a `Builder` can open: `Mutex`, `ReentrantMutex` and `RwLock`
+pub trait FromBuilder<T, Fmt> {
+    fn from_builder(builder: Builder<T, Fmt>, data: T) -> Self;
+}
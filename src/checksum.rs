@@ -0,0 +1,134 @@
+use crate::{Error, IoOp};
+use crc32fast::Hasher;
+use std::{
+    fs::{self, File},
+    io::{ErrorKind, Result as IoResult, Write},
+    path::{Path, PathBuf},
+};
+
+pub(crate) struct ChecksumWriter<W> {
+    writer: W,
+    hasher: Hasher,
+}
+
+impl<W: Write> ChecksumWriter<W> {
+    #[inline]
+    pub(crate) fn new(writer: W) -> Self {
+        Self {
+            writer,
+            hasher: Hasher::new(),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn finish(self) -> u32 {
+        self.hasher.finalize()
+    }
+}
+
+impl<W: Write> Write for ChecksumWriter<W> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        let len = self.writer.write(buf)?;
+        self.hasher.update(&buf[..len]);
+        Ok(len)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> IoResult<()> {
+        self.writer.flush()
+    }
+}
+
+// `<path>.sum` holds one hexadecimal checksum per line. The first one is of the current file, and a second one of the
+// file it replaced, in case the save was interrupted before the file was
+pub(crate) fn write_checksums(path: &Path, checksums: &[u32], sync: bool) -> IoResult<()> {
+    let mut file = File::create(checksum_path(path))?;
+
+    for checksum in checksums {
+        writeln!(file, "{:08x}", checksum)?;
+    }
+
+    if sync {
+        file.sync_all()?;
+    }
+
+    Ok(())
+}
+
+// The checksum of the temporary file also lists the one of the file it replaces. It is moved in place before the data,
+// so a crash between the two renames leaves a checksum that still matches the old file
+pub(crate) fn write_tmp_checksums(
+    path: &Path,
+    tmp_path: &Path,
+    checksum: u32,
+    sync: bool,
+) -> IoResult<()> {
+    let previous = match read_checksum(path)? {
+        Some(previous) => Some(previous),
+        // A file saved without checksums has none yet, so it is computed from the file once
+        None => match fs::read(path) {
+            Ok(data) => Some(crc32fast::hash(&data)),
+            Err(err) if err.kind() == ErrorKind::NotFound => None,
+            Err(err) => return Err(err),
+        },
+    };
+
+    let mut checksums = vec![checksum];
+    checksums.extend(previous);
+    write_checksums(tmp_path, &checksums, sync)
+}
+
+// Called when the data could not be moved after its checksum was. Writes the checksums without the one of the new file
+// next to the temporary file, to be moved back in place, and returns whether there were any
+pub(crate) fn write_restored_checksums(path: &Path, tmp_path: &Path) -> IoResult<bool> {
+    match read_checksums(path)? {
+        Some(checksums) if checksums.len() > 1 => {
+            write_checksums(tmp_path, &checksums[1..], false)?;
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
+
+// `None` if there is no checksum file, invalid lines are skipped
+pub(crate) fn read_checksums(path: &Path) -> IoResult<Option<Vec<u32>>> {
+    match fs::read_to_string(checksum_path(path)) {
+        Ok(checksums) => Ok(Some(
+            checksums
+                .lines()
+                .filter_map(|line| u32::from_str_radix(line.trim(), 16).ok())
+                .collect(),
+        )),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+// The checksum of the current file, `None` if there is none or it is not valid
+pub(crate) fn read_checksum(path: &Path) -> IoResult<Option<u32>> {
+    Ok(read_checksums(path)?.and_then(|checksums| checksums.first().copied()))
+}
+
+pub(crate) fn verify_checksum(path: &Path, data: &[u8]) -> Result<(), Error> {
+    let checksums = match read_checksums(path) {
+        Ok(Some(checksums)) => checksums,
+        Ok(None) => return Err(Error::ChecksumMissing),
+        Err(err) => {
+            return Err(Error::Io {
+                op: IoOp::Read,
+                source: err,
+            })
+        }
+    };
+
+    if checksums.contains(&crc32fast::hash(data)) {
+        Ok(())
+    } else {
+        Err(Error::ChecksumMismatch)
+    }
+}
+
+pub(crate) fn checksum_path(path: &Path) -> PathBuf {
+    crate::append_to_path(path, ".sum")
+}
@@ -0,0 +1,36 @@
+use crate::Error;
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    XChaCha20Poly1305, XNonce,
+};
+use std::io::{Error as IoError, ErrorKind};
+
+const NONCE_LEN: usize = 24;
+
+// A fresh random nonce is generated for every save and stored in front of the ciphertext
+pub(crate) fn encrypt(key: &[u8; 32], data: &[u8]) -> Result<Vec<u8>, Error> {
+    let cipher = XChaCha20Poly1305::new(key.into());
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+    let ciphertext = cipher
+        .encrypt(&nonce, data)
+        .map_err(|_| IoError::new(ErrorKind::InvalidInput, "data too large to encrypt"))?;
+
+    let mut file = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+    file.extend_from_slice(&nonce);
+    file.extend_from_slice(&ciphertext);
+    Ok(file)
+}
+
+pub(crate) fn decrypt(key: &[u8; 32], data: &[u8]) -> Result<Vec<u8>, Error> {
+    if data.len() < NONCE_LEN {
+        return Err(Error::Decryption);
+    }
+
+    let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+    let cipher = XChaCha20Poly1305::new(key.into());
+
+    cipher
+        .decrypt(XNonce::from_slice(nonce), ciphertext)
+        .map_err(|_| Error::Decryption)
+}
@@ -0,0 +1,388 @@
+use serde_json::{error::Category, Error as JsonError};
+use std::{
+    error::Error as StdError,
+    fmt::{Display, Formatter, Result as FmtResult},
+    io::{Error as IoError, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    Io {
+        op: IoOp,
+        source: IoError,
+    },
+    Load {
+        path: PathBuf,
+        source: Box<Error>,
+    },
+    Save {
+        path: PathBuf,
+        source: Box<Error>,
+    },
+    Json(JsonError),
+    /// Serialization error of a format implemented outside of jsave, with its message
+    Serialize(String),
+    /// Deserialization error of a format implemented outside of jsave, with its message
+    Deserialize(String),
+    FileLocked,
+    WouldBlock,
+    EmptyFile,
+    RoundtripFailed,
+    /// An option that the format does not support, with its name
+    UnsupportedFormat(&'static str),
+    #[cfg(feature = "toml")]
+    TomlSerialize(toml::ser::Error),
+    #[cfg(feature = "toml")]
+    TomlDeserialize(toml::de::Error),
+    #[cfg(feature = "yaml")]
+    Yaml(serde_yaml::Error),
+    #[cfg(feature = "bincode")]
+    Bincode(bincode::Error),
+    #[cfg(feature = "cbor")]
+    CborSerialize(ciborium::ser::Error<IoError>),
+    #[cfg(feature = "cbor")]
+    CborDeserialize(ciborium::de::Error<IoError>),
+    #[cfg(feature = "ron")]
+    RonSerialize(ron::Error),
+    #[cfg(feature = "ron")]
+    RonDeserialize(ron::error::SpannedError),
+    #[cfg(feature = "checksum")]
+    ChecksumMismatch,
+    /// The file has no `<path>.sum` next to it, while the lock verifies checksums
+    #[cfg(feature = "checksum")]
+    ChecksumMissing,
+    #[cfg(feature = "notify")]
+    Notify(notify::Error),
+    #[cfg(feature = "encryption")]
+    Decryption,
+    #[cfg(feature = "json5")]
+    Json5(json5::Error),
+    #[cfg(feature = "msgpack")]
+    MsgPackEncode(rmp_serde::encode::Error),
+    #[cfg(feature = "msgpack")]
+    MsgPackDecode(rmp_serde::decode::Error),
+}
+
+/// The file operation an `Error::Io` happened in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum IoOp {
+    Open,
+    Read,
+    Write,
+    Sync,
+    Rename,
+    Backup,
+    CreateDir,
+    SetPermissions,
+    Lock,
+    Watch,
+    Other,
+}
+
+impl Display for IoOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let op = match self {
+            Self::Open => "opening the file",
+            Self::Read => "reading the file",
+            Self::Write => "writing the file",
+            Self::Sync => "syncing the file",
+            Self::Rename => "renaming the file",
+            Self::Backup => "backing up the file",
+            Self::CreateDir => "creating the parent directory",
+            Self::SetPermissions => "setting the file permissions",
+            Self::Lock => "locking the file",
+            Self::Watch => "watching the file",
+            Self::Other => "accessing the file",
+        };
+
+        f.write_str(op)
+    }
+}
+
+pub(crate) trait IoContext<T> {
+    fn context(self, op: IoOp) -> Result<T, Error>;
+}
+
+impl<T> IoContext<T> for Result<T, IoError> {
+    #[inline]
+    fn context(self, op: IoOp) -> Result<T, Error> {
+        self.map_err(|source| Error::Io { op, source })
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Io { op, source } => write!(f, "IO error while {}: {}", op, source),
+            Self::Load { path, source } => {
+                write!(f, "failed to load {}: {}", path.display(), source)
+            }
+            Self::Save { path, source } => {
+                write!(f, "failed to save {}: {}", path.display(), source)
+            }
+            Self::Json(err) => write!(f, "JSON error: {}", err),
+            Self::Serialize(msg) => write!(f, "serialization error: {}", msg),
+            Self::Deserialize(msg) => write!(f, "deserialization error: {}", msg),
+            Self::FileLocked => write!(f, "file is locked by another process"),
+            Self::WouldBlock => write!(f, "the lock is held by another guard"),
+            Self::EmptyFile => write!(f, "file is empty"),
+            Self::RoundtripFailed => write!(
+                f,
+                "the saved data does not deserialize back to the same value"
+            ),
+            Self::UnsupportedFormat(option) => {
+                write!(f, "{} is not supported by this format", option)
+            }
+            #[cfg(feature = "toml")]
+            Self::TomlSerialize(err) => write!(f, "TOML serialization error: {}", err),
+            #[cfg(feature = "toml")]
+            Self::TomlDeserialize(err) => write!(f, "TOML deserialization error: {}", err),
+            #[cfg(feature = "yaml")]
+            Self::Yaml(err) => write!(f, "YAML error: {}", err),
+            #[cfg(feature = "bincode")]
+            Self::Bincode(err) => write!(f, "bincode error: {}", err),
+            #[cfg(feature = "cbor")]
+            Self::CborSerialize(err) => write!(f, "CBOR serialization error: {}", err),
+            #[cfg(feature = "cbor")]
+            Self::CborDeserialize(err) => write!(f, "CBOR deserialization error: {}", err),
+            #[cfg(feature = "ron")]
+            Self::RonSerialize(err) => write!(f, "RON serialization error: {}", err),
+            #[cfg(feature = "ron")]
+            Self::RonDeserialize(err) => write!(f, "RON deserialization error: {}", err),
+            #[cfg(feature = "checksum")]
+            Self::ChecksumMismatch => write!(f, "checksum mismatch"),
+            #[cfg(feature = "checksum")]
+            Self::ChecksumMissing => write!(f, "checksum file is missing"),
+            #[cfg(feature = "notify")]
+            Self::Notify(err) => write!(f, "file watcher error: {}", err),
+            #[cfg(feature = "encryption")]
+            Self::Decryption => write!(
+                f,
+                "decryption failed, the key is wrong or the file is corrupted"
+            ),
+            #[cfg(feature = "json5")]
+            Self::Json5(err) => write!(f, "JSON5 error: {}", err),
+            #[cfg(feature = "msgpack")]
+            Self::MsgPackEncode(err) => write!(f, "MessagePack serialization error: {}", err),
+            #[cfg(feature = "msgpack")]
+            Self::MsgPackDecode(err) => write!(f, "MessagePack deserialization error: {}", err),
+        }
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Load { source, .. } => Some(source),
+            Self::Save { source, .. } => Some(source),
+            Self::Json(err) => Some(err),
+            Self::Serialize(_) => None,
+            Self::Deserialize(_) => None,
+            Self::FileLocked => None,
+            Self::WouldBlock => None,
+            Self::EmptyFile => None,
+            Self::RoundtripFailed => None,
+            Self::UnsupportedFormat(_) => None,
+            #[cfg(feature = "toml")]
+            Self::TomlSerialize(err) => Some(err),
+            #[cfg(feature = "toml")]
+            Self::TomlDeserialize(err) => Some(err),
+            #[cfg(feature = "yaml")]
+            Self::Yaml(err) => Some(err),
+            #[cfg(feature = "bincode")]
+            Self::Bincode(err) => Some(err),
+            #[cfg(feature = "cbor")]
+            Self::CborSerialize(err) => Some(err),
+            #[cfg(feature = "cbor")]
+            Self::CborDeserialize(err) => Some(err),
+            #[cfg(feature = "ron")]
+            Self::RonSerialize(err) => Some(err),
+            #[cfg(feature = "ron")]
+            Self::RonDeserialize(err) => Some(err),
+            #[cfg(feature = "checksum")]
+            Self::ChecksumMismatch => None,
+            #[cfg(feature = "checksum")]
+            Self::ChecksumMissing => None,
+            #[cfg(feature = "notify")]
+            Self::Notify(err) => Some(err),
+            #[cfg(feature = "encryption")]
+            Self::Decryption => None,
+            #[cfg(feature = "json5")]
+            Self::Json5(err) => Some(err),
+            #[cfg(feature = "msgpack")]
+            Self::MsgPackEncode(err) => Some(err),
+            #[cfg(feature = "msgpack")]
+            Self::MsgPackDecode(err) => Some(err),
+        }
+    }
+}
+
+impl Error {
+    /// Returns the error without the `Load` or `Save` wrapper that names the file
+    #[inline]
+    pub fn root(&self) -> &Self {
+        match self {
+            Self::Load { source, .. } | Self::Save { source, .. } => source.root(),
+            err => err,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn loading(self, path: &Path) -> Self {
+        Self::Load {
+            path: path.to_path_buf(),
+            source: Box::new(self),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn saving(self, path: &Path) -> Self {
+        Self::Save {
+            path: path.to_path_buf(),
+            source: Box::new(self),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn is_not_found(&self) -> bool {
+        matches!(self.root(), Self::Io { source, .. } if source.kind() == ErrorKind::NotFound)
+    }
+
+    // A missing or empty file is where `init_or_else` falls back to the default data
+    #[inline]
+    pub(crate) fn is_missing_file(&self) -> bool {
+        self.is_not_found() || matches!(self.root(), Self::EmptyFile)
+    }
+
+    // Errors that may not happen again when the write is retried
+    #[inline]
+    pub(crate) fn is_transient(&self) -> bool {
+        matches!(
+            self.root(),
+            Self::Io { source, .. } if matches!(
+                source.kind(),
+                ErrorKind::Interrupted | ErrorKind::WouldBlock | ErrorKind::TimedOut
+            )
+        )
+    }
+
+    // IO errors coming out of a `Format` can not know what they were part of, so they are tagged afterwards
+    #[inline]
+    pub(crate) fn with_op(self, op: IoOp) -> Self {
+        match self {
+            Self::Io {
+                op: IoOp::Other,
+                source,
+            } => Self::Io { op, source },
+            err => err,
+        }
+    }
+}
+
+impl From<IoError> for Error {
+    #[inline]
+    fn from(err: IoError) -> Self {
+        Self::Io {
+            op: IoOp::Other,
+            source: err,
+        }
+    }
+}
+
+impl From<JsonError> for Error {
+    #[inline]
+    fn from(err: JsonError) -> Self {
+        // serde_json wraps failures of the underlying reader / writer, unwrap them back into IO errors
+        match err.classify() {
+            Category::Io => IoError::from(err).into(),
+            _ => Self::Json(err),
+        }
+    }
+}
+
+#[cfg(feature = "yaml")]
+impl From<serde_yaml::Error> for Error {
+    #[inline]
+    fn from(err: serde_yaml::Error) -> Self {
+        Self::Yaml(err)
+    }
+}
+
+#[cfg(feature = "bincode")]
+impl From<bincode::Error> for Error {
+    #[inline]
+    fn from(err: bincode::Error) -> Self {
+        match *err {
+            bincode::ErrorKind::Io(err) => err.into(),
+            err => Self::Bincode(Box::new(err)),
+        }
+    }
+}
+
+#[cfg(feature = "cbor")]
+impl From<ciborium::ser::Error<IoError>> for Error {
+    #[inline]
+    fn from(err: ciborium::ser::Error<IoError>) -> Self {
+        match err {
+            ciborium::ser::Error::Io(err) => err.into(),
+            err => Self::CborSerialize(err),
+        }
+    }
+}
+
+#[cfg(feature = "cbor")]
+impl From<ciborium::de::Error<IoError>> for Error {
+    #[inline]
+    fn from(err: ciborium::de::Error<IoError>) -> Self {
+        match err {
+            ciborium::de::Error::Io(err) => err.into(),
+            err => Self::CborDeserialize(err),
+        }
+    }
+}
+
+#[cfg(feature = "notify")]
+impl From<notify::Error> for Error {
+    #[inline]
+    fn from(err: notify::Error) -> Self {
+        match err.kind {
+            notify::ErrorKind::Io(err) => Self::Io {
+                op: IoOp::Watch,
+                source: err,
+            },
+            _ => Self::Notify(err),
+        }
+    }
+}
+
+#[cfg(feature = "msgpack")]
+impl From<rmp_serde::encode::Error> for Error {
+    #[inline]
+    fn from(err: rmp_serde::encode::Error) -> Self {
+        use rmp::encode::ValueWriteError;
+
+        match err {
+            rmp_serde::encode::Error::InvalidValueWrite(
+                ValueWriteError::InvalidMarkerWrite(err) | ValueWriteError::InvalidDataWrite(err),
+            ) => err.into(),
+            err => Self::MsgPackEncode(err),
+        }
+    }
+}
+
+#[cfg(feature = "msgpack")]
+impl From<rmp_serde::decode::Error> for Error {
+    #[inline]
+    fn from(err: rmp_serde::decode::Error) -> Self {
+        use rmp_serde::decode::Error as DecodeError;
+
+        match err {
+            DecodeError::InvalidMarkerRead(err) | DecodeError::InvalidDataRead(err) => err.into(),
+            err => Self::MsgPackDecode(err),
+        }
+    }
+}
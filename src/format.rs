@@ -0,0 +1,352 @@
+use crate::Error;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{ser::PrettyFormatter, Serializer, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+
+pub trait Format {
+    /// Whether the data can be converted to a `serde_json::Value` and back without changing it, which sorting the keys
+    /// relies on
+    const JSON_DATA: bool = false;
+
+    /// Whether the output is text, which `with_trailing_newline` can end with a newline
+    const TEXT: bool = false;
+
+    fn serialize<T, W>(writer: W, data: &T, pretty: bool) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+        W: Write;
+
+    // Formats without configurable indentation keep their default pretty-printing
+    #[inline]
+    fn serialize_indented<T, W>(writer: W, data: &T, _indent: &str) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+        W: Write,
+    {
+        Self::serialize(writer, data, true)
+    }
+
+    fn deserialize<T, R>(reader: R) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+        R: Read;
+}
+
+pub struct Json;
+
+impl Format for Json {
+    const JSON_DATA: bool = true;
+    const TEXT: bool = true;
+
+    #[inline]
+    fn serialize<T, W>(writer: W, data: &T, pretty: bool) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+        W: Write,
+    {
+        if pretty {
+            serde_json::to_writer_pretty(writer, data)?;
+        } else {
+            serde_json::to_writer(writer, data)?;
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    fn serialize_indented<T, W>(writer: W, data: &T, indent: &str) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+        W: Write,
+    {
+        let formatter = PrettyFormatter::with_indent(indent.as_bytes());
+        data.serialize(&mut Serializer::with_formatter(writer, formatter))?;
+        Ok(())
+    }
+
+    #[inline]
+    fn deserialize<T, R>(reader: R) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+        R: Read,
+    {
+        Ok(serde_json::from_reader(reader)?)
+    }
+}
+
+/// Stores a sequence as newline-delimited JSON, one item per line, so that new items can be appended with
+/// `save_append` instead of rewriting the whole file
+pub struct JsonLines;
+
+impl Format for JsonLines {
+    const JSON_DATA: bool = true;
+    const TEXT: bool = true;
+
+    #[inline]
+    fn serialize<T, W>(mut writer: W, data: &T, _: bool) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+        W: Write,
+    {
+        let items = match serde_json::to_value(data)? {
+            Value::Array(items) => items,
+            _ => {
+                return Err(Error::Json(serde::ser::Error::custom(
+                    "JSON Lines can only store sequences",
+                )))
+            }
+        };
+
+        for item in items {
+            serde_json::to_writer(&mut writer, &item)?;
+            writer.write_all(b"\n")?;
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    fn deserialize<T, R>(reader: R) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+        R: Read,
+    {
+        let mut items = Vec::new();
+
+        for line in BufReader::new(reader).lines() {
+            let line = line?;
+
+            if !line.trim().is_empty() {
+                items.push(serde_json::from_str::<Value>(&line)?);
+            }
+        }
+
+        Ok(serde_json::from_value(Value::Array(items))?)
+    }
+}
+
+#[cfg(feature = "toml")]
+pub struct Toml;
+
+#[cfg(feature = "toml")]
+impl Format for Toml {
+    const TEXT: bool = true;
+
+    #[inline]
+    fn serialize<T, W>(mut writer: W, data: &T, pretty: bool) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+        W: Write,
+    {
+        let data = if pretty {
+            toml::to_string_pretty(data)
+        } else {
+            toml::to_string(data)
+        }
+        .map_err(Error::TomlSerialize)?;
+
+        writer.write_all(data.as_bytes())?;
+        Ok(())
+    }
+
+    #[inline]
+    fn deserialize<T, R>(mut reader: R) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+        R: Read,
+    {
+        let mut data = String::new();
+        reader.read_to_string(&mut data)?;
+        toml::from_str(&data).map_err(Error::TomlDeserialize)
+    }
+}
+
+#[cfg(feature = "yaml")]
+pub struct Yaml;
+
+#[cfg(feature = "yaml")]
+impl Format for Yaml {
+    const TEXT: bool = true;
+
+    #[inline]
+    fn serialize<T, W>(writer: W, data: &T, _: bool) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+        W: Write,
+    {
+        serde_yaml::to_writer(writer, data)?;
+        Ok(())
+    }
+
+    #[inline]
+    fn deserialize<T, R>(reader: R) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+        R: Read,
+    {
+        Ok(serde_yaml::from_reader(reader)?)
+    }
+}
+
+#[cfg(feature = "bincode")]
+pub struct Bincode;
+
+#[cfg(feature = "bincode")]
+impl Format for Bincode {
+    #[inline]
+    fn serialize<T, W>(writer: W, data: &T, _: bool) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+        W: Write,
+    {
+        bincode::serialize_into(writer, data)?;
+        Ok(())
+    }
+
+    #[inline]
+    fn deserialize<T, R>(reader: R) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+        R: Read,
+    {
+        Ok(bincode::deserialize_from(reader)?)
+    }
+}
+
+#[cfg(feature = "cbor")]
+pub struct Cbor;
+
+#[cfg(feature = "cbor")]
+impl Format for Cbor {
+    #[inline]
+    fn serialize<T, W>(writer: W, data: &T, _: bool) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+        W: Write,
+    {
+        ciborium::ser::into_writer(data, writer)?;
+        Ok(())
+    }
+
+    #[inline]
+    fn deserialize<T, R>(reader: R) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+        R: Read,
+    {
+        Ok(ciborium::de::from_reader(reader)?)
+    }
+}
+
+#[cfg(feature = "ron")]
+pub struct Ron;
+
+#[cfg(feature = "ron")]
+impl Format for Ron {
+    const TEXT: bool = true;
+
+    // RON is meant to be edited by hand, so it is always pretty-printed
+    #[inline]
+    fn serialize<T, W>(writer: W, data: &T, _: bool) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+        W: Write,
+    {
+        ron_to_writer(writer, data, ron::ser::PrettyConfig::default())
+    }
+
+    #[inline]
+    fn serialize_indented<T, W>(writer: W, data: &T, indent: &str) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+        W: Write,
+    {
+        let config = ron::ser::PrettyConfig::default().indentor(indent.to_owned());
+        ron_to_writer(writer, data, config)
+    }
+
+    #[inline]
+    fn deserialize<T, R>(reader: R) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+        R: Read,
+    {
+        ron::de::from_reader(reader).map_err(Error::RonDeserialize)
+    }
+}
+
+#[cfg(feature = "ron")]
+fn ron_to_writer<T, W>(mut writer: W, data: &T, config: ron::ser::PrettyConfig) -> Result<(), Error>
+where
+    T: Serialize + ?Sized,
+    W: Write,
+{
+    let data = ron::ser::to_string_pretty(data, config).map_err(Error::RonSerialize)?;
+    writer.write_all(data.as_bytes())?;
+    Ok(())
+}
+
+/// Reads JSON5, which allows comments, unquoted keys and trailing commas, but writes standard JSON. Comments and other
+/// JSON5 syntax in the file are lost on the next save
+#[cfg(feature = "json5")]
+pub struct Json5;
+
+#[cfg(feature = "json5")]
+impl Format for Json5 {
+    const JSON_DATA: bool = true;
+    const TEXT: bool = true;
+
+    #[inline]
+    fn serialize<T, W>(writer: W, data: &T, pretty: bool) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+        W: Write,
+    {
+        Json::serialize(writer, data, pretty)
+    }
+
+    #[inline]
+    fn serialize_indented<T, W>(writer: W, data: &T, indent: &str) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+        W: Write,
+    {
+        Json::serialize_indented(writer, data, indent)
+    }
+
+    #[inline]
+    fn deserialize<T, R>(mut reader: R) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+        R: Read,
+    {
+        let mut data = String::new();
+        reader.read_to_string(&mut data)?;
+        json5::from_str(&data).map_err(Error::Json5)
+    }
+}
+
+#[cfg(feature = "msgpack")]
+pub struct MessagePack;
+
+#[cfg(feature = "msgpack")]
+impl Format for MessagePack {
+    #[inline]
+    fn serialize<T, W>(mut writer: W, data: &T, _: bool) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+        W: Write,
+    {
+        rmp_serde::encode::write(&mut writer, data)?;
+        Ok(())
+    }
+
+    #[inline]
+    fn deserialize<T, R>(reader: R) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+        R: Read,
+    {
+        Ok(rmp_serde::from_read(reader)?)
+    }
+}
@@ -0,0 +1,111 @@
+use crate::{Error, Format, Json};
+use serde::{de::DeserializeOwned, Serialize};
+use std::io::{Read, Write};
+
+// Accepts comments and trailing commas when loading, but always writes strict JSON
+pub(crate) struct LenientJson;
+
+impl Format for LenientJson {
+    #[inline]
+    fn serialize<T, W>(writer: W, data: &T, pretty: bool) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+        W: Write,
+    {
+        Json::serialize(writer, data, pretty)
+    }
+
+    #[inline]
+    fn deserialize<T, R>(mut reader: R) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+        R: Read,
+    {
+        let mut data = String::new();
+        reader.read_to_string(&mut data)?;
+        Ok(serde_json::from_str(&strip(&data))?)
+    }
+}
+
+// Comments and trailing commas are replaced with spaces rather than removed, so the positions in parse errors still
+// point into the original file
+fn strip(input: &str) -> String {
+    let mut output = strip_comments(input).into_bytes();
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for i in 0..output.len() {
+        match output[i] {
+            _ if escaped => escaped = false,
+            b'\\' if in_string => escaped = true,
+            b'"' => in_string = !in_string,
+            b',' if !in_string => {
+                let next = output[i + 1..]
+                    .iter()
+                    .find(|byte| !byte.is_ascii_whitespace());
+
+                if let Some(b']' | b'}') = next {
+                    output[i] = b' ';
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Only ASCII bytes outside of strings were replaced
+    String::from_utf8(output).unwrap()
+}
+
+fn strip_comments(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+    let mut in_string = false;
+    let mut escaped = false;
+
+    while let Some(c) = chars.next() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            output.push(c);
+            continue;
+        }
+
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                output.push(c);
+            }
+            ('/', Some('/')) => {
+                while let Some(&c) = chars.peek() {
+                    if c == '\n' {
+                        break;
+                    }
+                    output.push(' ');
+                    chars.next();
+                }
+                output.push(' ');
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                output.push_str("  ");
+                let mut prev = ' ';
+
+                for c in chars.by_ref() {
+                    output.push(if c == '\n' { '\n' } else { ' ' });
+
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+            }
+            _ => output.push(c),
+        }
+    }
+
+    output
+}
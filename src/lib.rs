@@ -1,25 +1,1102 @@
 #![doc = include_str!("../README.md")]
 
+pub mod format;
 pub mod mutex;
 pub mod remutex;
 pub mod rwlock;
 
-pub use crate::{mutex::Mutex, remutex::ReentrantMutex, rwlock::RwLock};
+mod append;
+mod autosave;
+mod backup;
+mod batch;
+mod builder;
+mod error;
+mod lenient;
+mod memory;
 
-use serde::{Deserialize, Serialize};
-use std::{fs::OpenOptions, io::Result, path::Path};
+#[cfg(feature = "checksum")]
+mod checksum;
 
-fn save_data_to_path<T>(data: &T, path: &Path) -> Result<()>
+#[cfg(feature = "encryption")]
+mod encryption;
+
+#[cfg(feature = "notify")]
+mod watch;
+
+pub use crate::{
+    autosave::AutosaveHandle,
+    batch::{save_all, Saveable},
+    builder::{Builder, FromBuilder},
+    error::{Error, IoOp},
+    format::{Format, Json, JsonLines},
+    mutex::Mutex,
+    remutex::ReentrantMutex,
+    rwlock::RwLock,
+};
+
+#[cfg(feature = "bincode")]
+pub use crate::format::Bincode;
+
+#[cfg(feature = "cbor")]
+pub use crate::format::Cbor;
+
+#[cfg(feature = "json5")]
+pub use crate::format::Json5;
+
+#[cfg(feature = "msgpack")]
+pub use crate::format::MessagePack;
+
+#[cfg(feature = "ron")]
+pub use crate::format::Ron;
+
+#[cfg(feature = "toml")]
+pub use crate::format::Toml;
+
+#[cfg(feature = "tokio")]
+pub use crate::autosave::AsyncAutosaveHandle;
+
+#[cfg(feature = "notify")]
+pub use crate::watch::WatchHandle;
+
+#[cfg(feature = "yaml")]
+pub use crate::format::Yaml;
+
+use crate::error::IoContext;
+use fs2::FileExt;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{
+        BufRead, BufWriter, Error as IoError, ErrorKind, IntoInnerError, Result as IoResult, Write,
+    },
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+#[cfg(unix)]
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+/// Serializes the data exactly like a lock with default options saves it, without touching the filesystem
+pub fn to_string<T: Serialize + ?Sized>(data: &T) -> Result<String, Error> {
+    let mut buf = Vec::new();
+    serialize_to_writer::<Json, _, _>(&mut buf, data, &Options::default())?;
+    Ok(String::from_utf8(buf).expect("serde_json only writes valid UTF-8"))
+}
+
+/// Deserializes the data exactly like a lock loads its file, without touching the filesystem
+pub fn from_str<T: DeserializeOwned>(s: &str) -> Result<T, Error> {
+    deserialize_from_reader::<Json, _, _>(s.as_bytes())
+}
+
+/// Loads the file exactly like `init` does, but only borrows the path and neither keeps a lock nor rewrites the file
+pub fn from_path<T: DeserializeOwned, P: AsRef<Path>>(path: P) -> Result<T, Error> {
+    load_data_from_path::<Json, _>(path.as_ref(), &Options::default())
+}
+
+/// Loads any JSON file as an untyped `Value`, e.g. to inspect it without knowing the type of its data
+pub fn read_value<P: AsRef<Path>>(path: P) -> Result<Value, Error> {
+    from_path(path)
+}
+
+/// Saves a `Value` atomically, exactly like a lock with default options saves its data
+pub fn write_value<P: AsRef<Path>>(path: P, value: &Value) -> Result<usize, Error> {
+    save_data_to_path::<Json, _>(value, path.as_ref(), &Options::default(), false)
+}
+
+fn load_data_from_path<F, T>(path: &Path, options: &Options) -> Result<T, Error>
+where
+    F: Format,
+    T: DeserializeOwned,
+{
+    options.persisted_lines.forget();
+
+    if let Some(memory) = &options.memory {
+        return memory::load::<F, _>(memory, options).map_err(|err| err.loading(path));
+    }
+
+    let file = options
+        .open_options()
+        .read(true)
+        .open(path)
+        .context(IoOp::Open)
+        .map_err(|err| err.loading(path))?;
+
+    load_data_from_file::<F, _>(file, path, options)
+}
+
+// Creating the file while opening it means that a file created by another process in the meantime is loaded, not
+// overwritten with the data of `f`
+fn load_or_create<F, T, D>(path: &Path, options: &Options, f: D) -> Result<(T, InitOutcome), Error>
+where
+    F: Format,
+    T: DeserializeOwned,
+    D: FnOnce() -> T,
+{
+    let file = options
+        .open_options()
+        .read(true)
+        .write(true)
+        .create(true)
+        .open(path)
+        .context(IoOp::Open)
+        .map_err(|err| err.loading(path))?;
+
+    match load_data_from_file::<F, _>(file, path, options) {
+        Ok(data) => Ok((data, InitOutcome::Loaded)),
+        Err(err) if matches!(err.root(), Error::EmptyFile) => Ok((f(), InitOutcome::Created)),
+        Err(err) => Err(err),
+    }
+}
+
+fn load_data_from_file<F, T>(file: File, path: &Path, options: &Options) -> Result<T, Error>
+where
+    F: Format,
+    T: DeserializeOwned,
+{
+    read_data_from_file::<F, _>(file, path, options).map_err(|err| err.loading(path))
+}
+
+// `path` is only used to find the checksum next to the file
+#[cfg_attr(not(feature = "checksum"), allow(unused_variables))]
+fn read_data_from_file<F, T>(file: File, path: &Path, options: &Options) -> Result<T, Error>
+where
+    F: Format,
+    T: DeserializeOwned,
+{
+    // The whole file is needed to authenticate it, so it can't be decrypted while being deserialized
+    #[cfg(feature = "encryption")]
+    if let Some(key) = &options.cipher {
+        let data = read_file(file)?;
+
+        if data.is_empty() {
+            return Err(Error::EmptyFile);
+        }
+
+        #[cfg(feature = "checksum")]
+        if options.checksum {
+            checksum::verify_checksum(path, &data)?;
+        }
+
+        let data = encryption::decrypt(key, &data)?;
+        return deserialize_from_reader::<F, _, _>(data.as_slice());
+    }
+
+    // An empty file is created before its first save, so it is reported as such rather than for its missing checksum
+    #[cfg(feature = "checksum")]
+    if options.checksum {
+        let data = read_file(file)?;
+
+        if data.is_empty() {
+            return Err(Error::EmptyFile);
+        }
+
+        checksum::verify_checksum(path, &data)?;
+        return deserialize_from_reader::<F, _, _>(data.as_slice());
+    }
+
+    deserialize_from_reader::<F, _, _>(std::io::BufReader::new(file))
+}
+
+#[cfg(any(feature = "checksum", feature = "encryption"))]
+fn read_file(mut file: File) -> Result<Vec<u8>, Error> {
+    let mut data = Vec::new();
+    std::io::Read::read_to_end(&mut file, &mut data).context(IoOp::Read)?;
+    Ok(data)
+}
+
+fn deserialize_from_reader<F, T, R>(mut reader: R) -> Result<T, Error>
+where
+    F: Format,
+    T: DeserializeOwned,
+    R: BufRead,
+{
+    if reader.fill_buf().context(IoOp::Read)?.is_empty() {
+        return Err(Error::EmptyFile);
+    }
+
+    // Compressed files are detected by their magic bytes, so plain files keep loading after compression is turned on
+    #[cfg(feature = "compression")]
+    if reader
+        .fill_buf()
+        .context(IoOp::Read)?
+        .starts_with(&GZIP_MAGIC)
+    {
+        return F::deserialize(flate2::bufread::GzDecoder::new(reader))
+            .map_err(|err| err.with_op(IoOp::Read));
+    }
+
+    F::deserialize(&mut reader).map_err(|err| err.with_op(IoOp::Read))
+}
+
+#[cfg_attr(not(feature = "encryption"), allow(unused_mut))]
+fn serialize_to_writer<F, T, W>(mut writer: W, data: &T, options: &Options) -> Result<(), Error>
+where
+    F: Format,
+    T: Serialize + ?Sized,
+    W: Write,
+{
+    #[cfg(feature = "encryption")]
+    if let Some(key) = &options.cipher {
+        let mut data_buf = Vec::new();
+        compress_to_writer::<F, _, _>(&mut data_buf, data, options)?;
+        let data = encryption::encrypt(key, &data_buf).map_err(|err| err.with_op(IoOp::Write))?;
+        return writer.write_all(&data).context(IoOp::Write);
+    }
+
+    compress_to_writer::<F, _, _>(writer, data, options).map_err(|err| err.with_op(IoOp::Write))
+}
+
+fn compress_to_writer<F, T, W>(writer: W, data: &T, options: &Options) -> Result<(), Error>
+where
+    F: Format,
+    T: Serialize + ?Sized,
+    W: Write,
+{
+    #[cfg(feature = "compression")]
+    if let Some(level) = options.compression {
+        let mut encoder = flate2::write::GzEncoder::new(writer, flate2::Compression::new(level));
+        serialize_with_indent::<F, _, _>(&mut encoder, data, options)?;
+        encoder.finish()?;
+        return Ok(());
+    }
+
+    serialize_with_indent::<F, _, _>(writer, data, options)
+}
+
+fn serialize_with_indent<F, T, W>(writer: W, data: &T, options: &Options) -> Result<(), Error>
+where
+    F: Format,
+    T: Serialize + ?Sized,
+    W: Write,
+{
+    if options.trailing_newline {
+        if !F::TEXT {
+            return Err(Error::UnsupportedFormat("with_trailing_newline"));
+        }
+
+        let mut writer = NewlineWriter::new(writer);
+        serialize_sorted::<F, _, _>(&mut writer, data, options)?;
+        return Ok(writer.finish()?);
+    }
+
+    serialize_sorted::<F, _, _>(writer, data, options)
+}
+
+fn serialize_sorted<F, T, W>(writer: W, data: &T, options: &Options) -> Result<(), Error>
+where
+    F: Format,
+    T: Serialize + ?Sized,
+    W: Write,
+{
+    if options.sort_keys && !F::JSON_DATA {
+        return Err(Error::UnsupportedFormat("with_sorted_keys"));
+    }
+
+    if options.canonical && !F::JSON_DATA {
+        return Err(Error::UnsupportedFormat("with_canonical"));
+    }
+
+    // Maps like `HashMap` are written in arbitrary order, so the data is sorted as a `Value` first
+    if options.sorts_keys() {
+        let value = sort_keys(serde_json::to_value(data)?);
+        return format_to_writer::<F, _, _>(writer, &value, options);
+    }
+
+    format_to_writer::<F, _, _>(writer, data, options)
+}
+
+fn format_to_writer<F, T, W>(writer: W, data: &T, options: &Options) -> Result<(), Error>
+where
+    F: Format,
+    T: Serialize + ?Sized,
+    W: Write,
+{
+    if options.canonical {
+        return F::serialize(writer, data, false);
+    }
+
+    match &options.indent {
+        Some(indent) if options.pretty => F::serialize_indented(writer, data, indent),
+        _ => F::serialize(writer, data, options.pretty),
+    }
+}
+
+fn sort_keys(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries = map
+                .into_iter()
+                .map(|(key, value)| (key, sort_keys(value)))
+                .collect::<Vec<_>>();
+
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Value::Object(entries.into_iter().collect())
+        }
+        Value::Array(values) => Value::Array(values.into_iter().map(sort_keys).collect()),
+        value => value,
+    }
+}
+
+#[cfg(feature = "compression")]
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+// Doubling the wait of every retry stops here
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(60);
+
+// The path of locks created with `init_memory`, which is never opened
+const MEMORY_PATH: &str = ":memory:";
+
+// What the methods that work on the file itself return for locks created with `init_memory`
+fn no_file_error() -> IoError {
+    IoError::new(
+        ErrorKind::Unsupported,
+        "the lock is in memory and has no file",
+    )
+}
+
+/// Whether `open` loaded an existing file, or created it with the default data because it was missing or empty
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InitOutcome {
+    Loaded,
+    Created,
+}
+
+#[derive(Clone)]
+struct Options {
+    pretty: bool,
+    indent: Option<String>,
+    backups: usize,
+    mode: Option<u32>,
+    create_dirs: bool,
+    sort_keys: bool,
+    canonical: bool,
+    trailing_newline: bool,
+    temp_dir: Option<PathBuf>,
+    on_save: Option<Arc<SaveHook>>,
+    verify: Option<VerifyFn>,
+    pre_save: Option<Arc<PreSaveFn>>,
+    memory: Option<memory::Memory>,
+    retries: usize,
+    retry_backoff: Duration,
+    last_saved: SaveTime,
+    persisted_lines: append::PersistedLines,
+    open_options: Option<OpenOptions>,
+    #[cfg(feature = "checksum")]
+    checksum: bool,
+    #[cfg(feature = "compression")]
+    compression: Option<u32>,
+    #[cfg(feature = "encryption")]
+    cipher: Option<[u8; 32]>,
+}
+
+impl Options {
+    #[inline]
+    const fn new() -> Self {
+        Self {
+            pretty: cfg!(feature = "pretty"),
+            indent: None,
+            backups: 0,
+            mode: None,
+            create_dirs: false,
+            sort_keys: false,
+            canonical: false,
+            trailing_newline: false,
+            temp_dir: None,
+            on_save: None,
+            verify: None,
+            pre_save: None,
+            memory: None,
+            retries: 0,
+            retry_backoff: Duration::ZERO,
+            last_saved: SaveTime::new(),
+            persisted_lines: append::PersistedLines::new(),
+            open_options: None,
+            #[cfg(feature = "checksum")]
+            checksum: false,
+            #[cfg(feature = "compression")]
+            compression: None,
+            #[cfg(feature = "encryption")]
+            cipher: None,
+        }
+    }
+}
+
+impl Options {
+    // The hooks that cast the data to the type of the lock can't be used to save anything else
+    #[inline]
+    fn without_typed_hooks(&self) -> Self {
+        Self {
+            verify: None,
+            pre_save: None,
+            ..self.clone()
+        }
+    }
+
+    // Saves to another path go to the filesystem even for a lock in memory
+    #[inline]
+    pub(crate) fn without_memory(&self) -> Self {
+        Self {
+            memory: None,
+            ..self.clone()
+        }
+    }
+
+    #[inline]
+    fn sorts_keys(&self) -> bool {
+        self.sort_keys || self.canonical
+    }
+
+    // Custom options keep their flags, e.g. `custom_flags`, while the access mode is set for each use
+    #[inline]
+    fn open_options(&self) -> OpenOptions {
+        self.open_options.clone().unwrap_or_else(OpenOptions::new)
+    }
+
+    #[inline]
+    fn saved(&self, path: &Path, len: usize) {
+        self.last_saved.set_now();
+        self.persisted_lines.forget();
+
+        if let Some(on_save) = &self.on_save {
+            on_save(path, len);
+        }
+    }
+}
+
+type SaveHook = dyn Fn(&Path, usize) + Send + Sync;
+
+// Options don't know the type of the data, so the lock picks the function that loads it back when verification is
+// enabled
+type VerifyFn = fn(&Path, &Options) -> Result<Value, Error>;
+
+// Writes a transformed copy of the data to the temporary file. It is created by the lock for the type of its data, and
+// takes a thin pointer to it for the same reason as `DropSaveFn`
+type PreSaveFn = dyn Fn(*const u8, &Path, &Options, bool) -> Result<usize, Error> + Send + Sync;
+
+// Milliseconds since the Unix epoch, or 0 before the first save
+struct SaveTime(AtomicU64);
+
+impl SaveTime {
+    #[inline]
+    const fn new() -> Self {
+        Self(AtomicU64::new(0))
+    }
+
+    #[inline]
+    fn set_now(&self) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        self.0.store(now.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    #[inline]
+    fn get(&self) -> Option<SystemTime> {
+        match self.0.load(Ordering::Relaxed) {
+            0 => None,
+            millis => Some(UNIX_EPOCH + Duration::from_millis(millis)),
+        }
+    }
+}
+
+// Copies of the options save elsewhere, like `save_as` and `clone_to`, so they start without a save
+impl Clone for SaveTime {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+}
+
+impl Default for Options {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Takes the data as a thin pointer rather than `&T`, so that a lock with save on drop can still be coerced to an
+// unsized one. The pointer must point to a `T`
+type DropSaveFn = unsafe fn(*const u8, &Path, &Options, bool) -> Result<usize, Error>;
+
+unsafe fn save_erased_data<F, T>(
+    data: *const u8,
+    path: &Path,
+    options: &Options,
+    sync: bool,
+) -> Result<usize, Error>
+where
+    F: Format,
+    T: Serialize,
+{
+    save_data_to_path::<F, T>(&*(data as *const T), path, options, sync)
+}
+
+// Mapped guards no longer know the type of the whole data, so they save it through this
+trait SaveLocked {
+    // The caller must hold the lock exclusively
+    unsafe fn save_locked_data(&self) -> Result<usize, Error>;
+    unsafe fn autosave_locked_data(&self);
+    fn set_dirty(&self);
+}
+
+// A lock with unsized data can't be turned into a trait object itself, but a reference to it can
+type LockRef<'a> = Box<dyn SaveLocked + Send + Sync + 'a>;
+
+impl<L: SaveLocked + ?Sized> SaveLocked for &L {
+    unsafe fn save_locked_data(&self) -> Result<usize, Error> {
+        (**self).save_locked_data()
+    }
+
+    unsafe fn autosave_locked_data(&self) {
+        (**self).autosave_locked_data()
+    }
+
+    fn set_dirty(&self) {
+        (**self).set_dirty()
+    }
+}
+
+fn save_data_to_path<F, T>(
+    data: &T,
+    path: &Path,
+    options: &Options,
+    sync: bool,
+) -> Result<usize, Error>
+where
+    F: Format,
+    T: Serialize + ?Sized,
+{
+    let res = write_tmp_file::<F, _>(data, path, options, sync).and_then(|len| {
+        commit_tmp_file(path, options)?;
+        Ok(len)
+    });
+
+    let len = match res {
+        Ok(len) => len,
+        Err(err) => {
+            remove_tmp_file(path, options);
+            return Err(err);
+        }
+    };
+
+    if sync && options.memory.is_none() {
+        sync_parent_dir(path)
+            .context(IoOp::Sync)
+            .map_err(|err| err.saving(path))?;
+    }
+
+    options.saved(path, len);
+    Ok(len)
+}
+
+// Serializing twice is cheaper than a write that wakes up everything watching the file
+// Writes the temporary file like every save, so hooks like `with_pre_save` apply, but only moves it into place if its
+// content differs from the file
+fn save_data_if_changed<F, T>(data: &T, path: &Path, options: &Options) -> Result<bool, Error>
+where
+    F: Format,
+    T: Serialize + ?Sized,
+{
+    let res = write_tmp_file::<F, _>(data, path, options, false).and_then(|len| {
+        if tmp_file_matches(path, options).map_err(|err| err.saving(path))? {
+            return Ok(None);
+        }
+
+        commit_tmp_file(path, options)?;
+        Ok(Some(len))
+    });
+
+    match res {
+        Ok(Some(len)) => {
+            options.saved(path, len);
+            Ok(true)
+        }
+        Ok(None) => {
+            remove_tmp_file(path, options);
+            Ok(false)
+        }
+        Err(err) => {
+            remove_tmp_file(path, options);
+            Err(err)
+        }
+    }
+}
+
+fn tmp_file_matches(path: &Path, options: &Options) -> Result<bool, Error> {
+    if let Some(memory) = &options.memory {
+        return Ok(memory::is_unchanged(memory));
+    }
+
+    let current = match fs::read(path) {
+        Ok(current) => current,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(false),
+        Err(err) => {
+            return Err(Error::Io {
+                op: IoOp::Read,
+                source: err,
+            })
+        }
+    };
+
+    Ok(fs::read(tmp_path(path, options)).context(IoOp::Read)? == current)
+}
+
+fn write_tmp_file<F, T>(
+    data: &T,
+    path: &Path,
+    options: &Options,
+    sync: bool,
+) -> Result<usize, Error>
 where
-    T: Serialize + for<'de> Deserialize<'de> + ?Sized,
+    F: Format,
+    T: Serialize + ?Sized,
 {
-    let file = OpenOptions::new().write(true).truncate(true).open(&path)?;
+    let _guard = TmpFileGuard { path, options };
+    let mut retries = options.retries;
+    let mut backoff = options.retry_backoff;
 
-    #[cfg(feature = "pretty")]
-    serde_json::to_writer_pretty(file, data)?;
+    loop {
+        let res = match &options.pre_save {
+            Some(pre_save) => pre_save(data as *const T as *const u8, path, options, sync),
+            None => write_data_to_tmp_file::<F, _>(data, path, options, sync),
+        };
 
-    #[cfg(not(feature = "pretty"))]
-    serde_json::to_writer(file, data)?;
+        match res {
+            Err(err) if retries > 0 && err.is_transient() => {
+                retries -= 1;
+                thread::sleep(backoff);
+                backoff = backoff.saturating_mul(2).min(MAX_RETRY_BACKOFF);
+            }
+            res => return res.map_err(|err| err.saving(path)),
+        }
+    }
+}
+
+// A `Serialize` impl that panics leaves a partly written temporary file behind, which is removed while unwinding
+struct TmpFileGuard<'a> {
+    path: &'a Path,
+    options: &'a Options,
+}
+
+impl Drop for TmpFileGuard<'_> {
+    #[inline]
+    fn drop(&mut self) {
+        if thread::panicking() {
+            remove_tmp_file(self.path, self.options);
+        }
+    }
+}
+
+fn pre_save_fn<F, T, H>(hook: H) -> Arc<PreSaveFn>
+where
+    F: Format + 'static,
+    T: Serialize + Clone + 'static,
+    H: Fn(&mut T) + Send + Sync + 'static,
+{
+    Arc::new(move |data, path, options, sync| {
+        // The pointer always comes from the lock that created this function
+        let mut data = unsafe { &*(data as *const T) }.clone();
+        hook(&mut data);
+        write_data_to_tmp_file::<F, _>(&data, path, options, sync)
+    })
+}
+
+fn write_data_to_tmp_file<F, T>(
+    data: &T,
+    path: &Path,
+    options: &Options,
+    sync: bool,
+) -> Result<usize, Error>
+where
+    F: Format,
+    T: Serialize + ?Sized,
+{
+    if let Some(memory) = &options.memory {
+        return memory::write::<F, _>(memory, data, options);
+    }
+
+    if options.create_dirs {
+        match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => {
+                fs::create_dir_all(dir).context(IoOp::CreateDir)?
+            }
+            _ => {}
+        }
+    }
+
+    // A configured mode takes precedence, otherwise the file keeps the permissions of the one it replaces
+    let permissions = match options.mode {
+        #[cfg(unix)]
+        Some(mode) => Some(fs::Permissions::from_mode(mode)),
+        _ => fs::metadata(path)
+            .ok()
+            .map(|metadata| metadata.permissions()),
+    };
+
+    let tmp_path = tmp_path(path, options);
+    let len =
+        write_data_to_path::<F, _>(data, path, &tmp_path, options, sync, permissions.is_some())
+            .and_then(|len| {
+                if let Some(verify) = options.verify {
+                    verify_roundtrip(data, &tmp_path, options, verify)?;
+                }
+                Ok(len)
+            })
+            .map_err(|err| {
+                remove_tmp_file(path, options);
+                err
+            })?;
+
+    if let Some(permissions) = permissions {
+        fs::set_permissions(&tmp_path, permissions).context(IoOp::SetPermissions)?;
+    }
+
+    Ok(len)
+}
+
+fn verify_roundtrip<T>(
+    data: &T,
+    tmp_path: &Path,
+    options: &Options,
+    verify: VerifyFn,
+) -> Result<(), Error>
+where
+    T: Serialize + ?Sized,
+{
+    // Comparing values rather than bytes ignores the order of maps like `HashMap`, which differs between instances
+    if verify(tmp_path, options)? != serde_json::to_value(data)? {
+        return Err(Error::RoundtripFailed);
+    }
 
     Ok(())
 }
+
+fn load_value<F, T>(path: &Path, options: &Options) -> Result<Value, Error>
+where
+    F: Format,
+    T: Serialize + DeserializeOwned,
+{
+    let data = load_data_from_path::<F, T>(path, options)?;
+    Ok(serde_json::to_value(&data)?)
+}
+
+fn commit_tmp_file(path: &Path, options: &Options) -> Result<(), Error> {
+    if let Some(memory) = &options.memory {
+        memory::commit(memory);
+        return Ok(());
+    }
+
+    move_tmp_files(path, options).map_err(|err| err.saving(path))
+}
+
+fn move_tmp_files(path: &Path, options: &Options) -> Result<(), Error> {
+    let tmp_path = tmp_path(path, options);
+
+    if options.backups > 0 {
+        backup::rotate_backups(path, options).context(IoOp::Backup)?;
+    }
+
+    // The checksum also lists the one of the file it replaces, so it is moved first and matches whichever file is in
+    // place if the save is interrupted between the two
+    #[cfg(feature = "checksum")]
+    if options.checksum {
+        move_tmp_file(
+            &checksum::checksum_path(&tmp_path),
+            &checksum::checksum_path(path),
+            options,
+        )
+        .context(IoOp::Rename)?;
+    }
+
+    let res = move_tmp_file(&tmp_path, path, options).context(IoOp::Rename);
+
+    // The file was not replaced, so the checksum of the new data is dropped again, as the next save takes the first
+    // checksum to be the one of the file it replaces
+    #[cfg(feature = "checksum")]
+    if res.is_err() && options.checksum {
+        if let Ok(true) = checksum::write_restored_checksums(path, &tmp_path) {
+            let _ = move_tmp_file(
+                &checksum::checksum_path(&tmp_path),
+                &checksum::checksum_path(path),
+                options,
+            );
+        }
+    }
+
+    res
+}
+
+// `fs::rename` also replaces an existing target on Windows, where it uses `MoveFileExW` with
+// `MOVEFILE_REPLACE_EXISTING`
+fn move_tmp_file(from: &Path, to: &Path, options: &Options) -> IoResult<()> {
+    match fs::rename(from, to) {
+        // A temporary directory on another filesystem can't be renamed from, so the file is copied over the target,
+        // which is not atomic
+        Err(err) if options.temp_dir.is_some() && crosses_devices(&err) => {
+            fs::copy(from, to)?;
+            fs::remove_file(from)
+        }
+        res => res,
+    }
+}
+
+// `ErrorKind::CrossesDevices` is newer than the minimum supported Rust version, so the OS error code is checked
+#[cfg_attr(not(any(unix, windows)), allow(unused_variables))]
+fn crosses_devices(err: &IoError) -> bool {
+    // `EXDEV` on Unix, `ERROR_NOT_SAME_DEVICE` on Windows
+    #[cfg(unix)]
+    const CROSSES_DEVICES: i32 = 18;
+    #[cfg(windows)]
+    const CROSSES_DEVICES: i32 = 17;
+    #[cfg(not(any(unix, windows)))]
+    return false;
+
+    #[cfg(any(unix, windows))]
+    return err.raw_os_error() == Some(CROSSES_DEVICES);
+}
+
+fn remove_tmp_file(path: &Path, options: &Options) {
+    if let Some(memory) = &options.memory {
+        return memory::discard(memory);
+    }
+
+    let tmp_path = tmp_path(path, options);
+    let _ = fs::remove_file(&tmp_path);
+
+    #[cfg(feature = "checksum")]
+    if options.checksum {
+        let _ = fs::remove_file(checksum::checksum_path(&tmp_path));
+    }
+}
+
+// Writes to `tmp_path`, while `path` is the file it replaces, whose checksum is kept. A `private` file is only readable
+// by the owner until its final permissions are set, regardless of the umask
+#[cfg_attr(any(not(unix), not(feature = "checksum")), allow(unused_variables))]
+fn write_data_to_path<F, T>(
+    data: &T,
+    path: &Path,
+    tmp_path: &Path,
+    options: &Options,
+    sync: bool,
+    private: bool,
+) -> Result<usize, Error>
+where
+    F: Format,
+    T: Serialize + ?Sized,
+{
+    let mut open_options = options.open_options();
+    open_options.write(true).create(true).truncate(true);
+
+    #[cfg(unix)]
+    if private {
+        open_options.mode(0o600);
+    }
+
+    let mut file = open_options.open(tmp_path).context(IoOp::Open)?;
+
+    // The mode only applies to a new file, not to one left behind by an interrupted save
+    #[cfg(unix)]
+    if private {
+        file.set_permissions(fs::Permissions::from_mode(0o600))
+            .context(IoOp::SetPermissions)?;
+    }
+
+    let mut writer = CountingWriter::new(BufWriter::new(&mut file));
+
+    #[cfg(feature = "checksum")]
+    let checksum = if options.checksum {
+        let mut writer = checksum::ChecksumWriter::new(&mut writer);
+        serialize_to_writer::<F, _, _>(&mut writer, data, options)?;
+        Some(writer.finish())
+    } else {
+        serialize_to_writer::<F, _, _>(&mut writer, data, options)?;
+        None
+    };
+
+    #[cfg(not(feature = "checksum"))]
+    serialize_to_writer::<F, _, _>(&mut writer, data, options)?;
+
+    let len = writer.len;
+
+    // Dropping the `BufWriter` would silently discard errors of the last write
+    writer
+        .into_inner()
+        .into_inner()
+        .map_err(IntoInnerError::into_error)
+        .context(IoOp::Write)?;
+
+    if sync {
+        file.sync_all().context(IoOp::Sync)?;
+    }
+
+    // The checksum is written next to the temporary file, and moved along with it
+    #[cfg(feature = "checksum")]
+    if let Some(checksum) = checksum {
+        checksum::write_tmp_checksums(path, tmp_path, checksum, sync).context(IoOp::Write)?;
+    }
+
+    Ok(len)
+}
+
+struct CountingWriter<W> {
+    writer: W,
+    len: usize,
+}
+
+impl<W: Write> CountingWriter<W> {
+    #[inline]
+    fn new(writer: W) -> Self {
+        Self { writer, len: 0 }
+    }
+
+    #[inline]
+    fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        let len = self.writer.write(buf)?;
+        self.len += len;
+        Ok(len)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> IoResult<()> {
+        self.writer.flush()
+    }
+}
+
+// Formats like TOML already end their output with a newline, which is not doubled
+struct NewlineWriter<W> {
+    writer: W,
+    last: Option<u8>,
+}
+
+impl<W: Write> NewlineWriter<W> {
+    #[inline]
+    fn new(writer: W) -> Self {
+        Self { writer, last: None }
+    }
+
+    #[inline]
+    fn finish(mut self) -> IoResult<()> {
+        if self.last != Some(b'\n') {
+            self.writer.write_all(b"\n")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for NewlineWriter<W> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        let len = self.writer.write(buf)?;
+
+        if len > 0 {
+            self.last = Some(buf[len - 1]);
+        }
+
+        Ok(len)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> IoResult<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(feature = "tokio")]
+async fn spawn_blocking<F, R>(f: F) -> Result<R, Error>
+where
+    F: FnOnce() -> Result<R, Error> + Send + 'static,
+    R: Send + 'static,
+{
+    match tokio::task::spawn_blocking(f).await {
+        Ok(res) => res,
+        Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+        Err(err) => Err(std::io::Error::from(err).into()),
+    }
+}
+
+// Only a missing file moves on to the next path, a file that exists but fails to load is reported
+fn init_from_paths<I, L, F>(paths: I, mut init: F) -> Result<L, Error>
+where
+    I: IntoIterator<Item = PathBuf>,
+    F: FnMut(PathBuf) -> Result<L, Error>,
+{
+    let mut last_err = None;
+
+    for path in paths {
+        match init(path) {
+            Err(err) if err.is_not_found() => last_err = Some(err),
+            res => return res,
+        }
+    }
+
+    Err(last_err.unwrap_or_else(|| Error::Io {
+        op: IoOp::Open,
+        source: std::io::Error::new(ErrorKind::NotFound, "no paths to load the data from"),
+    }))
+}
+
+// The data file is replaced on every save, so the lock is taken on a separate file that is never moved
+fn lock_file(path: &Path) -> Result<File, Error> {
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(append_to_path(path, ".lock"))
+        .context(IoOp::Open)?;
+
+    match file.try_lock_exclusive() {
+        Ok(()) => Ok(file),
+        Err(err) if err.raw_os_error() == fs2::lock_contended_error().raw_os_error() => {
+            Err(Error::FileLocked)
+        }
+        Err(err) => Err(Error::Io {
+            op: IoOp::Lock,
+            source: err,
+        }),
+    }
+}
+
+// Syncing the directory makes the `rename` itself durable. Directories can not be opened as files on Windows
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) -> IoResult<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    File::open(dir)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_parent_dir(_: &Path) -> IoResult<()> {
+    Ok(())
+}
+
+// The temporary file lives next to the target so that the final `rename` stays on the same filesystem, unless
+// another directory is configured
+fn tmp_path(path: &Path, options: &Options) -> PathBuf {
+    match (&options.temp_dir, path.file_name()) {
+        (Some(dir), Some(file_name)) => append_to_path(&dir.join(file_name), ".tmp"),
+        _ => append_to_path(path, ".tmp"),
+    }
+}
+
+fn append_to_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
@@ -0,0 +1,65 @@
+use crate::{Error, Format, Options};
+use parking_lot::Mutex;
+use serde::{de::DeserializeOwned, Serialize};
+use std::sync::Arc;
+
+// Stands in for the file and its temporary file of a lock created with `init_memory`
+#[derive(Default)]
+pub(crate) struct MemoryFile {
+    data: Vec<u8>,
+    pending: Option<Vec<u8>>,
+}
+
+pub(crate) type Memory = Arc<Mutex<MemoryFile>>;
+
+#[cfg_attr(not(feature = "encryption"), allow(unused_variables))]
+pub(crate) fn load<F, T>(memory: &Memory, options: &Options) -> Result<T, Error>
+where
+    F: Format,
+    T: DeserializeOwned,
+{
+    let file = memory.lock();
+
+    #[cfg(feature = "encryption")]
+    if let Some(key) = &options.cipher {
+        if file.data.is_empty() {
+            return Err(Error::EmptyFile);
+        }
+
+        let data = crate::encryption::decrypt(key, &file.data)?;
+        return crate::deserialize_from_reader::<F, _, _>(data.as_slice());
+    }
+
+    crate::deserialize_from_reader::<F, _, _>(file.data.as_slice())
+}
+
+pub(crate) fn write<F, T>(memory: &Memory, data: &T, options: &Options) -> Result<usize, Error>
+where
+    F: Format,
+    T: Serialize + ?Sized,
+{
+    let mut buf = Vec::new();
+    crate::serialize_to_writer::<F, _, _>(&mut buf, data, options)?;
+
+    let len = buf.len();
+    memory.lock().pending = Some(buf);
+    Ok(len)
+}
+
+pub(crate) fn commit(memory: &Memory) {
+    let mut file = memory.lock();
+
+    if let Some(data) = file.pending.take() {
+        file.data = data;
+    }
+}
+
+pub(crate) fn discard(memory: &Memory) {
+    memory.lock().pending = None;
+}
+
+// Whether the pending data is the same as the committed one
+pub(crate) fn is_unchanged(memory: &Memory) -> bool {
+    let file = memory.lock();
+    file.pending.as_ref() == Some(&file.data)
+}
@@ -1,57 +1,689 @@
+use crate::{
+    append::Append,
+    batch::{PendingSave, Sealed},
+    builder::FromBuilder,
+    lenient::LenientJson,
+    AutosaveHandle, Builder, DropSaveFn, Error, Format, InitOutcome, Json, JsonLines, LockRef,
+    Options, SaveLocked,
+};
 use parking_lot::{
     MappedMutexGuard as InnerMappedMutexGuard, Mutex as InnerMutex, MutexGuard as InnerMutexGuard,
 };
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+#[cfg(feature = "tokio")]
+use crate::AsyncAutosaveHandle;
+#[cfg(feature = "notify")]
+use crate::WatchHandle;
 use std::{
     fmt::{Debug, Display, Formatter, Result as FmtResult},
-    fs::OpenOptions,
-    io::Error,
+    fs::{self, File, Metadata, OpenOptions},
+    io::{Result as IoResult, Write},
+    marker::PhantomData,
+    mem::{self, ManuallyDrop},
     ops::{Deref, DerefMut},
     path::{Path, PathBuf},
-    time::{Duration, Instant},
+    ptr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime},
 };
 
-pub struct Mutex<T: ?Sized> {
+pub struct Mutex<T: ?Sized, Fmt = Json> {
     file_path: PathBuf,
+    options: Options,
+    file_lock: Option<File>,
+    format: PhantomData<Fmt>,
+    autosave: bool,
+    dirty: AtomicBool,
+    needs_flush: AtomicBool,
+    last_save_error: InnerMutex<Option<Error>>,
+    drop_save: Option<DropSaveFn>,
+    drop_error_handler: Option<Box<dyn Fn(Error) + Send + Sync>>,
     data: InnerMutex<T>,
 }
 
-impl<T> Mutex<T>
+impl<T, Fmt> Mutex<T, Fmt> {
+    /// Creates the lock without touching the file, so it can be used in const contexts. The data is written on the
+    /// first save
+    #[inline]
+    pub const fn new_unchecked(data: T, file_path: PathBuf) -> Self {
+        Self {
+            data: parking_lot::const_mutex(data),
+            file_path,
+            options: Options::new(),
+            file_lock: None,
+            format: PhantomData,
+            autosave: false,
+            dirty: AtomicBool::new(true),
+            needs_flush: AtomicBool::new(false),
+            last_save_error: parking_lot::const_mutex(None),
+            drop_save: None,
+            drop_error_handler: None,
+        }
+    }
+}
+
+impl<T, Fmt> Mutex<T, Fmt>
 where
-    T: Serialize + for<'de> Deserialize<'de> + ?Sized,
+    T: Serialize + DeserializeOwned,
+    Fmt: Format,
 {
     #[inline]
     pub fn init<P: Into<PathBuf>>(file_path: P) -> Result<Self, Error> {
+        Builder::new(file_path).format::<Fmt>().open()
+    }
+
+    /// Like `init`, but does not rewrite the file after reading it. Changes can still be written with `save`
+    #[inline]
+    pub fn init_readonly<P: Into<PathBuf>>(file_path: P) -> Result<Self, Error> {
+        let file_path = file_path.into();
+        let data = crate::load_data_from_path::<Fmt, _>(&file_path, &Options::default())?;
+        Ok(Self::new(data, file_path))
+    }
+
+    /// Reads the data from an already opened file instead of opening `file_path`, which is only used for later saves.
+    /// The file is not rewritten
+    #[inline]
+    pub fn init_from_file<P: Into<PathBuf>>(file: File, file_path: P) -> Result<Self, Error> {
+        let file_path = file_path.into();
+        let data = crate::load_data_from_file::<Fmt, _>(file, &file_path, &Options::default())?;
+        Ok(Self::new(data, file_path))
+    }
+
+    /// Like `init`, but holds an advisory lock on `<path>.lock` until the lock is dropped, and fails with
+    /// `Error::FileLocked` if another process holds it. The lock file is never renamed, so saves replace the data file
+    /// while the lock is still held and a process that waits for it never reads a half-written or outdated file
+    #[inline]
+    pub fn init_exclusive<P: Into<PathBuf>>(file_path: P) -> Result<Self, Error> {
+        let file_path = file_path.into();
+        let file_lock = crate::lock_file(&file_path)?;
+        let mut lock = Self::init(file_path)?;
+        lock.file_lock = Some(file_lock);
+        Ok(lock)
+    }
+
+    #[cfg(feature = "encryption")]
+    #[inline]
+    pub fn init_encrypted<P: Into<PathBuf>>(file_path: P, key: [u8; 32]) -> Result<Self, Error> {
         let file_path = file_path.into();
 
-        let data = {
-            let read = OpenOptions::new().read(true).open(&file_path)?;
-            serde_json::from_reader(read)?
+        let options = Options {
+            cipher: Some(key),
+            ..Default::default()
         };
 
-        crate::save_data_to_path(&data, &file_path)?;
+        let data = crate::load_data_from_path::<Fmt, _>(&file_path, &options)?;
+        crate::save_data_to_path::<Fmt, _>(&data, &file_path, &options, false)?;
+        Ok(Self::new(data, file_path).with_cipher(key))
+    }
 
-        Ok(Self {
-            data: InnerMutex::new(data),
-            file_path,
-        })
+    #[inline]
+    pub fn init_arc<P: Into<PathBuf>>(file_path: P) -> Result<Arc<Self>, Error> {
+        Self::init(file_path).map(Arc::new)
+    }
+
+    #[inline]
+    pub fn init_with_arc<P: Into<PathBuf>>(data: T, file_path: P) -> Result<Arc<Self>, Error> {
+        Self::init_with(data, file_path).map(Arc::new)
     }
 
     #[inline]
     pub fn init_with<P: Into<PathBuf>>(data: T, file_path: P) -> Result<Self, Error> {
         let file_path = file_path.into();
+        crate::save_data_to_path::<Fmt, _>(&data, &file_path, &Options::default(), false)?;
+        Ok(Self::new(data, file_path))
+    }
+
+    /// Creates a lock that saves to and reloads from memory instead of a file, e.g. for tests. Its path is `:memory:`,
+    /// and the file options like backups or permissions have no effect
+    #[inline]
+    pub fn init_memory(data: T) -> Result<Self, Error> {
+        let mut lock = Self::new(data, PathBuf::from(crate::MEMORY_PATH));
+        lock.options.memory = Some(Arc::default());
+        crate::save_data_to_path::<Fmt, _>(
+            lock.data.get_mut().deref(),
+            &lock.file_path,
+            &lock.options,
+            false,
+        )?;
+        Ok(lock)
+    }
+
+    #[inline]
+    pub fn init_with_unsaved<P: Into<PathBuf>>(data: T, file_path: P) -> Self {
+        let mut lock = Self::new(data, file_path.into());
+        // Nothing has been written yet, so the first `save` must not be skipped
+        *lock.dirty.get_mut() = true;
+        lock
+    }
+
+    #[inline]
+    pub fn init_or_default<P: Into<PathBuf>>(file_path: P) -> Result<Self, Error>
+    where
+        T: Default,
+    {
+        Self::init_or_else(file_path, T::default)
+    }
+
+    /// Like `init_or_default`, but also tells whether the file was loaded or created
+    #[inline]
+    pub fn open<P: Into<PathBuf>>(file_path: P) -> Result<(Self, InitOutcome), Error>
+    where
+        T: Default,
+    {
+        Self::init_or_else_with_outcome(file_path, T::default)
+    }
+
+    /// Like `open`, but creates the file while opening it, so that a file created by another process in the meantime is
+    /// loaded instead of overwritten. A new or empty file is initialized with `T::default()`
+    #[inline]
+    pub fn open_or_create<P: Into<PathBuf>>(file_path: P) -> Result<(Self, InitOutcome), Error>
+    where
+        T: Default,
+    {
+        let file_path = file_path.into();
+        let (data, outcome) =
+            crate::load_or_create::<Fmt, _, _>(&file_path, &Options::default(), T::default)?;
+        Self::init_with(data, file_path).map(|lock| (lock, outcome))
+    }
 
-        crate::save_data_to_path(&data, &file_path)?;
+    #[inline]
+    pub fn init_or_else<P, F>(file_path: P, f: F) -> Result<Self, Error>
+    where
+        P: Into<PathBuf>,
+        F: FnOnce() -> T,
+    {
+        Self::init_or_else_with_outcome(file_path, f).map(|(lock, _)| lock)
+    }
 
-        Ok(Self {
+    fn init_or_else_with_outcome<P, F>(file_path: P, f: F) -> Result<(Self, InitOutcome), Error>
+    where
+        P: Into<PathBuf>,
+        F: FnOnce() -> T,
+    {
+        let file_path = file_path.into();
+
+        let (data, outcome) =
+            match crate::load_data_from_path::<Fmt, _>(&file_path, &Options::default()) {
+                Ok(data) => (data, InitOutcome::Loaded),
+                Err(err) if err.is_missing_file() => (f(), InitOutcome::Created),
+                Err(err) => return Err(err),
+            };
+
+        Self::init_with(data, file_path).map(|lock| (lock, outcome))
+    }
+
+    /// Loads the first of `paths` that exists, and saves to it from then on. If none exists, the error of the last one
+    /// is returned
+    #[inline]
+    pub fn init_from_paths<I>(paths: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = PathBuf>,
+    {
+        crate::init_from_paths(paths, Self::init)
+    }
+
+    /// Like `init`, but if the file exists and can't be loaded, e.g. because it is corrupt, loads the newest backup
+    /// written with `with_backups` that can be, and saves it to `file_path`. Fails with the error of the file if none
+    /// can
+    #[inline]
+    pub fn init_with_recovery<P: Into<PathBuf>>(file_path: P) -> Result<Self, Error> {
+        let file_path = file_path.into();
+        let data = crate::backup::load_with_recovery::<Fmt, _>(&file_path, &Options::default())?;
+        Self::init_with(data, file_path)
+    }
+
+    #[inline]
+    pub fn init_with_migration<P, F>(file_path: P, migrate: F) -> Result<Self, Error>
+    where
+        P: Into<PathBuf>,
+        F: FnOnce(Value) -> Value,
+    {
+        let file_path = file_path.into();
+        let value = crate::load_data_from_path::<Fmt, Value>(&file_path, &Options::default())?;
+        let data = serde_json::from_value(migrate(value))
+            .map_err(|err| Error::from(err).loading(&file_path))?;
+        Self::init_with(data, file_path)
+    }
+
+    /// Loads a file written by `save_projection`, and builds the full data from the saved part with `build`. The file
+    /// is left as it is
+    #[inline]
+    pub fn init_projection<P, S, F>(file_path: P, build: F) -> Result<Self, Error>
+    where
+        P: Into<PathBuf>,
+        S: DeserializeOwned,
+        F: FnOnce(S) -> T,
+    {
+        let file_path = file_path.into();
+        let part = crate::load_data_from_path::<Fmt, S>(&file_path, &Options::default())?;
+        Ok(Self::new(build(part), file_path))
+    }
+
+    #[inline]
+    fn new(data: T, file_path: PathBuf) -> Self {
+        Self {
             data: InnerMutex::new(data),
             file_path,
+            options: Options::default(),
+            file_lock: None,
+            format: PhantomData,
+            autosave: false,
+            dirty: AtomicBool::new(false),
+            needs_flush: AtomicBool::new(false),
+            last_save_error: InnerMutex::new(None),
+            drop_save: None,
+            drop_error_handler: None,
+        }
+    }
+
+    #[inline]
+    pub fn with_autosave(mut self, autosave: bool) -> Self {
+        self.autosave = autosave;
+        self
+    }
+
+    /// Saves the data one last time when the lock is dropped, if it was modified. Errors are passed to the handler set
+    /// with `set_drop_error_handler`
+    #[inline]
+    pub fn with_save_on_drop(mut self, save_on_drop: bool) -> Self {
+        self.drop_save = if save_on_drop {
+            Some(crate::save_erased_data::<Fmt, T>)
+        } else {
+            None
+        };
+        self
+    }
+
+    #[inline]
+    pub fn set_drop_error_handler<F>(&mut self, handler: F)
+    where
+        F: Fn(Error) + Send + Sync + 'static,
+    {
+        self.drop_error_handler = Some(Box::new(handler));
+    }
+
+    #[inline]
+    pub fn with_backups(mut self, count: usize) -> Self {
+        self.options.backups = count;
+        self
+    }
+
+    /// Retries writing the file up to `count` times when it fails with an `Interrupted`, `WouldBlock` or `TimedOut` IO
+    /// error. The first retry waits for `backoff`, and every further one twice as long as the one before, up to a
+    /// minute
+    #[inline]
+    pub fn with_retries(mut self, count: usize, backoff: Duration) -> Self {
+        self.options.retries = count;
+        self.options.retry_backoff = backoff;
+        self
+    }
+
+    /// Opens the file with `open_options` to save and reload it, e.g. with `custom_flags` to not follow symlinks. The
+    /// read, write and create flags are set by jsave. Use `Builder::open_options` to also apply them to the first load
+    #[inline]
+    pub fn with_open_options(mut self, open_options: OpenOptions) -> Self {
+        self.options.open_options = Some(open_options);
+        self
+    }
+
+    #[inline]
+    pub fn with_indent<S: Into<String>>(mut self, indent: S) -> Self {
+        self.options.pretty = true;
+        self.options.indent = Some(indent.into());
+        self
+    }
+
+    /// Writes the keys of every map in sorted order, so the file only changes when the data does. The data is converted
+    /// to a `serde_json::Value` first, so saving fails with `Error::UnsupportedFormat` on formats other than JSON, JSON
+    /// Lines and JSON5
+    #[inline]
+    pub fn with_sorted_keys(mut self, sorted: bool) -> Self {
+        self.options.sort_keys = sorted;
+        self
+    }
+
+    /// Ends the file with a newline, as many text tools expect. Output that already ends with one is left as it is.
+    /// Saving fails with `Error::UnsupportedFormat` on binary formats
+    #[inline]
+    pub fn with_trailing_newline(mut self, trailing_newline: bool) -> Self {
+        self.options.trailing_newline = trailing_newline;
+        self
+    }
+
+    /// Writes the same bytes for the same data, e.g. to sign the file: keys are sorted like with `with_sorted_keys`,
+    /// and the output is never pretty-printed. JSON numbers are written in their shortest exact form, while NaN and
+    /// infinite floats are written as `null`. Saving fails with `Error::UnsupportedFormat` on formats other than JSON,
+    /// JSON Lines and JSON5
+    #[inline]
+    pub fn with_canonical(mut self, canonical: bool) -> Self {
+        self.options.canonical = canonical;
+        self
+    }
+
+    /// Writes the temporary file into `dir` instead of next to the file. If `dir` is on another filesystem, the file
+    /// can't be renamed into place and is copied over instead, so saves are no longer atomic
+    #[inline]
+    pub fn with_temp_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.options.temp_dir = Some(dir.into());
+        self
+    }
+
+    /// Calls `f` with the path and the number of bytes written after every successful save, whichever method triggered
+    /// it
+    #[inline]
+    pub fn with_on_save<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&Path, usize) + Send + Sync + 'static,
+    {
+        self.options.on_save = Some(Arc::new(f));
+        self
+    }
+
+    /// Applies `f` to a copy of the data right before every save, e.g. to leave secrets out of the file. The data in
+    /// the lock is not changed, and `save_if_changed` compares the file to the data as changed by `f`
+    #[inline]
+    pub fn with_pre_save<F>(mut self, f: F) -> Self
+    where
+        T: Clone + 'static,
+        Fmt: 'static,
+        F: Fn(&mut T) + Send + Sync + 'static,
+    {
+        self.options.pre_save = Some(crate::pre_save_fn::<Fmt, T, F>(f));
+        self
+    }
+
+    /// Reads every save back before it replaces the file, and fails with `Error::RoundtripFailed` if the data does not
+    /// deserialize to the same value. Both are compared as `serde_json::Value`
+    #[inline]
+    pub fn with_verify_roundtrip(mut self, verify: bool) -> Self {
+        self.options.verify = if verify {
+            Some(crate::load_value::<Fmt, T>)
+        } else {
+            None
+        };
+        self
+    }
+
+    #[inline]
+    pub fn with_mode(mut self, mode: u32) -> Self {
+        self.options.mode = Some(mode);
+        self
+    }
+
+    #[inline]
+    pub fn with_create_dirs(mut self, create_dirs: bool) -> Self {
+        self.options.create_dirs = create_dirs;
+        self
+    }
+
+    #[cfg(feature = "encryption")]
+    #[inline]
+    pub fn with_cipher(mut self, key: [u8; 32]) -> Self {
+        self.options.cipher = Some(key);
+        self
+    }
+
+    /// Stores a CRC32 checksum of the file in `<path>.sum` on every save, and verifies it when the file is loaded
+    /// again. A missing checksum fails loading with `Error::ChecksumMissing`, a different one with
+    /// `Error::ChecksumMismatch`. Set it with `Builder::checksum` for the first load to be verified as well
+    #[cfg(feature = "checksum")]
+    #[inline]
+    pub fn with_checksum(mut self, checksum: bool) -> Self {
+        self.options.checksum = checksum;
+        self
+    }
+
+    #[cfg(feature = "compression")]
+    #[inline]
+    pub fn with_compression(mut self, level: u32) -> Self {
+        self.options.compression = Some(level);
+        self
+    }
+
+    #[inline]
+    pub fn into_inner(mut self) -> T {
+        self.save_before_drop();
+        let mut lock = ManuallyDrop::new(self);
+
+        // `Drop` must not run, so the data is moved out and all other fields are dropped by hand. The pattern names
+        // every field, so adding one fails to compile until it is dropped here too
+        let Mutex {
+            file_path,
+            options,
+            file_lock,
+            format,
+            autosave,
+            dirty,
+            needs_flush,
+            last_save_error,
+            drop_save,
+            drop_error_handler,
+            data,
+        } = &mut *lock;
+
+        unsafe {
+            ptr::drop_in_place(file_path);
+            ptr::drop_in_place(options);
+            ptr::drop_in_place(file_lock);
+            ptr::drop_in_place(format);
+            ptr::drop_in_place(autosave);
+            ptr::drop_in_place(dirty);
+            ptr::drop_in_place(needs_flush);
+            ptr::drop_in_place(last_save_error);
+            ptr::drop_in_place(drop_save);
+            ptr::drop_in_place(drop_error_handler);
+            ptr::read(data).into_inner()
+        }
+    }
+
+    /// Clones the data out of the lock, without saving it
+    #[inline]
+    pub fn snapshot(&self) -> T
+    where
+        T: Clone,
+    {
+        self.data.lock().clone()
+    }
+
+    /// Same as `snapshot`
+    #[inline]
+    pub fn get(&self) -> T
+    where
+        T: Clone,
+    {
+        self.snapshot()
+    }
+
+    /// Saves a copy of the data to `file_path`, and returns an independent lock of it with the same options
+    #[inline]
+    pub fn clone_to<P: Into<PathBuf>>(&self, file_path: P) -> Result<Self, Error>
+    where
+        T: Clone,
+    {
+        let file_path = file_path.into();
+        let data = self.snapshot();
+        let options = self.options.without_memory();
+        crate::save_data_to_path::<Fmt, _>(&data, &file_path, &options, false)?;
+
+        let mut lock = Self::new(data, file_path);
+        lock.options = options;
+        lock.autosave = self.autosave;
+        Ok(lock)
+    }
+
+    /// Like `replace`, but drops the previous value
+    #[inline]
+    pub fn set(&self, data: T) -> Result<(), Error> {
+        self.replace(data).map(drop)
+    }
+
+    /// Swaps in `data`, saves it and returns the previous value. If the save fails, the previous value is put back
+    #[inline]
+    pub fn replace(&self, data: T) -> Result<T, Error> {
+        let mut guard = self.data.lock();
+        let old = mem::replace(guard.deref_mut(), data);
+
+        match crate::save_data_to_path::<Fmt, _>(
+            guard.deref(),
+            &self.file_path,
+            &self.options,
+            false,
+        ) {
+            Ok(_) => {
+                self.dirty.store(false, Ordering::Relaxed);
+                self.needs_flush.store(false, Ordering::Relaxed);
+                Ok(old)
+            }
+            Err(err) => {
+                *guard = old;
+                Err(err)
+            }
+        }
+    }
+
+    /// Discards the changes made since the last save by reloading the file. Same as `reload`
+    #[inline]
+    pub fn revert(&self) -> Result<(), Error> {
+        self.reload()
+    }
+
+    #[inline]
+    pub fn reload(&self) -> Result<(), Error> {
+        self.reload_locked(&mut self.data.lock())
+    }
+
+    /// Like `reload`, but passes the loaded data to `merge` to move it into the current data, e.g. to reuse its
+    /// allocations
+    #[inline]
+    pub fn reload_into<F>(&self, merge: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut T, T),
+    {
+        let mut data = self.data.lock();
+        let loaded = crate::load_data_from_path::<Fmt, _>(&self.file_path, &self.options)?;
+        merge(&mut data, loaded);
+        self.dirty.store(false, Ordering::Relaxed);
+        self.needs_flush.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+
+    #[inline]
+    pub fn try_reload_for(&self, timeout: Duration) -> Option<Result<(), Error>> {
+        self.data
+            .try_lock_for(timeout)
+            .map(|mut data| self.reload_locked(&mut data))
+    }
+
+    #[inline]
+    fn reload_locked(&self, data: &mut T) -> Result<(), Error> {
+        *data = crate::load_data_from_path::<Fmt, _>(&self.file_path, &self.options)?;
+        self.dirty.store(false, Ordering::Relaxed);
+        self.needs_flush.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Calls `f` in a background thread whenever the file is modified on disk, e.g. to `reload` it. Bursts of
+    /// modifications are reported once. Saves made through this lock are reported too
+    #[cfg(feature = "notify")]
+    #[inline]
+    pub fn watch<F>(&self, f: F) -> Result<WatchHandle, Error>
+    where
+        F: FnMut() + Send + 'static,
+    {
+        if self.is_memory() {
+            return Err(Error::Io {
+                op: crate::IoOp::Watch,
+                source: crate::no_file_error(),
+            });
+        }
+
+        WatchHandle::spawn(&self.file_path, f)
+    }
+
+    /// Saves the data in a background thread every `interval` if it has been modified since the last save. The returned
+    /// handle must be kept alive, as dropping it stops the thread
+    #[inline]
+    pub fn spawn_autosave(self: &Arc<Self>, interval: Duration) -> AutosaveHandle
+    where
+        T: Send + 'static,
+        Fmt: Send + Sync + 'static,
+    {
+        let mutex = Arc::clone(self);
+
+        AutosaveHandle::spawn(interval, move || {
+            if mutex.dirty.load(Ordering::Relaxed) {
+                if let Some(Err(err)) = mutex.try_save() {
+                    *mutex.last_save_error.lock() = Some(err);
+                }
+            }
         })
     }
 
+    /// Saves the data on the blocking thread pool of tokio, so that the file IO does not stall the async runtime
+    #[cfg(feature = "tokio")]
     #[inline]
-    pub fn into_inner(self) -> T {
-        self.data.into_inner()
+    pub async fn save_async(self: &Arc<Self>) -> Result<usize, Error>
+    where
+        T: Send + 'static,
+        Fmt: Send + Sync + 'static,
+    {
+        let mutex = Arc::clone(self);
+        crate::spawn_blocking(move || mutex.save()).await
+    }
+
+    /// Reloads the data on the blocking thread pool of tokio, like `save_async`
+    #[cfg(feature = "tokio")]
+    #[inline]
+    pub async fn reload_async(self: &Arc<Self>) -> Result<(), Error>
+    where
+        T: Send + 'static,
+        Fmt: Send + Sync + 'static,
+    {
+        let mutex = Arc::clone(self);
+        crate::spawn_blocking(move || mutex.reload()).await
+    }
+
+    /// Like `spawn_autosave`, but saves with `save_async` in a task on the current tokio runtime. The returned handle
+    /// must be kept alive, as dropping it aborts the task
+    #[cfg(feature = "tokio")]
+    #[inline]
+    pub fn spawn_autosave_async(self: &Arc<Self>, interval: Duration) -> AsyncAutosaveHandle
+    where
+        T: Send + 'static,
+        Fmt: Send + Sync + 'static,
+    {
+        let mutex = Arc::clone(self);
+
+        AsyncAutosaveHandle::spawn(interval, move || {
+            let mutex = Arc::clone(&mutex);
+
+            async move {
+                if mutex.dirty.load(Ordering::Relaxed) {
+                    if let Err(err) = mutex.save_async().await {
+                        *mutex.last_save_error.lock() = Some(err);
+                    }
+                }
+            }
+        })
+    }
+}
+
+impl<T, Fmt> Mutex<T, Fmt>
+where
+    T: Serialize + ?Sized,
+    Fmt: Format,
+{
+    /// The underlying parking_lot lock. Changes made through it are neither autosaved nor tracked, so `mark_dirty` and
+    /// `save` have to be called to persist them
+    #[inline]
+    pub fn inner(&self) -> &InnerMutex<T> {
+        &self.data
     }
 
     #[inline]
@@ -59,11 +691,91 @@ where
         &self.file_path
     }
 
+    /// Whether the lock was created with `init_memory`, in which case `path` is only the placeholder `:memory:`, and
+    /// `file_metadata` and `watch` fail
+    #[inline]
+    pub fn is_memory(&self) -> bool {
+        self.options.memory.is_some()
+    }
+
+    /// When the data was last written to the file by this lock, without locking it. Saving a part of the data with
+    /// `save_projection` does not count
+    #[inline]
+    pub fn last_saved(&self) -> Option<SystemTime> {
+        self.options.last_saved.get()
+    }
+
+    /// Metadata of the file on disk, e.g. when it was last saved. The data is not locked
+    #[inline]
+    pub fn file_metadata(&self) -> IoResult<Metadata> {
+        if self.is_memory() {
+            return Err(crate::no_file_error());
+        }
+
+        fs::metadata(&self.file_path)
+    }
+
+    /// The lock taken by `init_exclusive` stays on the lock file of the original path
+    #[inline]
+    pub fn set_path<P: Into<PathBuf>>(&mut self, file_path: P) {
+        self.file_path = file_path.into();
+        *self.dirty.get_mut() = true;
+    }
+
+    /// Like `set_path`, but saves the data to the new path right away
+    #[inline]
+    pub fn set_path_and_save<P: Into<PathBuf>>(&mut self, file_path: P) -> Result<usize, Error> {
+        self.set_path(file_path);
+        self.save()
+    }
+
+    #[inline]
+    pub fn is_pretty(&self) -> bool {
+        self.options.pretty
+    }
+
+    #[inline]
+    pub fn set_pretty(&mut self, pretty: bool) {
+        if self.options.pretty != pretty {
+            self.options.pretty = pretty;
+            *self.dirty.get_mut() = true;
+        }
+    }
+
     #[inline]
     pub fn get_mut(&mut self) -> &mut T {
+        *self.dirty.get_mut() = true;
         self.data.get_mut()
     }
 
+    #[inline]
+    pub fn is_dirty(&self) -> bool {
+        self.dirty.load(Ordering::Relaxed)
+    }
+
+    /// Whether a guard was force-unlocked without saving its changes since the data was last saved or reloaded. The
+    /// flag is only kept in memory, so it does not survive the process, and a new lock always starts with it unset
+    #[inline]
+    pub fn needs_flush(&self) -> bool {
+        self.needs_flush.load(Ordering::Relaxed)
+    }
+
+    /// Marks the data as modified, so the next save writes the file even if no change was made through a guard
+    #[inline]
+    pub fn mark_dirty(&self) {
+        self.dirty.store(true, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn is_autosave(&self) -> bool {
+        self.autosave
+    }
+
+    #[inline]
+    pub fn take_last_save_error(&self) -> Option<Error> {
+        self.last_save_error.lock().take()
+    }
+
     #[inline]
     pub fn is_locked(&self) -> bool {
         self.data.is_locked()
@@ -75,15 +787,31 @@ where
     }
 
     #[inline]
-    pub fn lock(&self) -> MutexGuard<T> {
+    pub fn lock(&self) -> MutexGuard<'_, T, Fmt> {
         MutexGuard {
             mutex: self,
             guard: self.data.lock(),
         }
     }
 
+    /// Like `lock`, but the guard holds a clone of the `Arc` instead of borrowing the lock
     #[inline]
-    pub fn try_lock(&self) -> Option<MutexGuard<T>> {
+    pub fn lock_arc(self: &Arc<Self>) -> ArcMutexGuard<T, Fmt>
+    where
+        T: 'static,
+        Fmt: 'static,
+    {
+        // The `Arc` stored next to the guard keeps the lock alive for as long as the guard
+        let mutex = unsafe { &*Arc::as_ptr(self) };
+
+        ArcMutexGuard {
+            guard: mutex.lock(),
+            mutex: Arc::clone(self),
+        }
+    }
+
+    #[inline]
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T, Fmt>> {
         self.data.try_lock().map(|g| MutexGuard {
             mutex: self,
             guard: g,
@@ -91,7 +819,7 @@ where
     }
 
     #[inline]
-    pub fn try_lock_for(&self, timeout: Duration) -> Option<MutexGuard<T>> {
+    pub fn try_lock_for(&self, timeout: Duration) -> Option<MutexGuard<'_, T, Fmt>> {
         self.data.try_lock_for(timeout).map(|g| MutexGuard {
             mutex: self,
             guard: g,
@@ -99,7 +827,7 @@ where
     }
 
     #[inline]
-    pub fn try_lock_until(&self, timeout: Instant) -> Option<MutexGuard<T>> {
+    pub fn try_lock_until(&self, timeout: Instant) -> Option<MutexGuard<'_, T, Fmt>> {
         self.data.try_lock_until(timeout).map(|g| MutexGuard {
             mutex: self,
             guard: g,
@@ -107,86 +835,378 @@ where
     }
 
     #[inline]
-    pub fn save(&self) -> Result<(), Error> {
+    pub fn save(&self) -> Result<usize, Error> {
+        let data = self.data.lock();
+        self.save_locked(data.deref(), false)
+    }
+
+    #[inline]
+    pub fn save_durable(&self) -> Result<usize, Error> {
+        let data = self.data.lock();
+        self.save_locked(data.deref(), true)
+    }
+
+    #[inline]
+    pub fn save_as<P: AsRef<Path>>(&self, path: P) -> Result<usize, Error> {
+        let data = self.data.lock();
+        crate::save_data_to_path::<Fmt, _>(
+            data.deref(),
+            path.as_ref(),
+            &self.options.without_memory(),
+            false,
+        )
+    }
+
+    /// Saves like `save`, even if the data was not modified, but only replaces the file if what was written differs
+    /// from its current content, and returns whether it did. Encrypted files never match, as every save uses a new
+    /// nonce
+    #[inline]
+    pub fn save_if_changed(&self) -> Result<bool, Error> {
+        self.save_if_changed_locked(self.data.lock().deref())
+    }
+
+    /// Runs `f` on the locked data and saves it before unlocking. If the save fails, the changes are kept and the lock
+    /// stays dirty. If `f` panics, the lock is released without saving and the panic continues, but changes `f` made
+    /// before panicking stay in the data and are written by the next save. `with_write` discards them instead
+    #[inline]
+    pub fn update<F, R>(&self, f: F) -> Result<R, Error>
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        self.update_locked(&mut self.data.lock(), f)
+    }
+
+    /// Like `update`, but runs `f` on a copy of the data that only replaces it once `f` returns, so a panic leaves the
+    /// data as it was
+    #[inline]
+    pub fn with_write<F, R>(&self, f: F) -> Result<R, Error>
+    where
+        T: Clone,
+        F: FnOnce(&mut T) -> R,
+    {
+        self.update(|data| {
+            let mut copy = data.clone();
+            let res = f(&mut copy);
+            *data = copy;
+            res
+        })
+    }
+
+    /// Applies `f` to the locked data with each of `items`, and saves once after all of them
+    #[inline]
+    pub fn modify_many<I, F>(&self, items: I, mut f: F) -> Result<(), Error>
+    where
+        I: IntoIterator,
+        F: FnMut(&mut T, I::Item),
+    {
+        self.update(|data| items.into_iter().for_each(|item| f(data, item)))
+    }
+
+    #[inline]
+    pub fn try_update_for<F, R>(&self, timeout: Duration, f: F) -> Option<Result<R, Error>>
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        self.data
+            .try_lock_for(timeout)
+            .map(|mut data| self.update_locked(&mut data, f))
+    }
+
+    fn update_locked<F, R>(&self, data: &mut T, f: F) -> Result<R, Error>
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        // A panic in `f` unwinds before the data is marked as modified, so it is not saved right away
+        let res = f(data);
+        self.dirty.store(true, Ordering::Relaxed);
+        self.save_locked(data, false)?;
+        Ok(res)
+    }
+
+    /// Saves only the part of the data returned by `f`, e.g. to leave out runtime caches, which `init_projection` loads
+    /// back. The data is marked as modified afterwards, so `save` still writes all of it
+    #[inline]
+    pub fn save_projection<S, F>(&self, f: F) -> Result<usize, Error>
+    where
+        S: Serialize + ?Sized,
+        F: FnOnce(&T) -> &S,
+    {
+        let data = self.data.lock();
+        let options = self.options.without_typed_hooks();
+        let len =
+            crate::save_data_to_path::<Fmt, _>(f(data.deref()), &self.file_path, &options, false)?;
+        self.dirty.store(true, Ordering::Relaxed);
+        self.options.persisted_lines.forget();
+        Ok(len)
+    }
+
+    #[inline]
+    pub fn save_to_writer<W: Write>(&self, writer: W) -> Result<(), Error> {
         let data = self.data.lock();
-        crate::save_data_to_path(data.deref(), &self.file_path)
+        crate::serialize_to_writer::<Fmt, _, _>(writer, data.deref(), &self.options)
     }
 
     #[inline]
-    pub fn try_save(&self) -> Option<Result<(), Error>> {
+    pub fn try_save(&self) -> Option<Result<usize, Error>> {
         self.data
             .try_lock()
-            .map(|data| crate::save_data_to_path(data.deref(), &self.file_path))
+            .map(|data| self.save_locked(data.deref(), false))
     }
 
     #[inline]
-    pub fn try_save_for(&self, timeout: Duration) -> Option<Result<(), Error>> {
+    pub fn try_save_for(&self, timeout: Duration) -> Option<Result<usize, Error>> {
         self.data
             .try_lock_for(timeout)
-            .map(|data| crate::save_data_to_path(data.deref(), &self.file_path))
+            .map(|data| self.save_locked(data.deref(), false))
     }
 
     #[inline]
-    pub fn try_save_until(&self, timeout: Instant) -> Option<Result<(), Error>> {
+    pub fn try_save_until(&self, timeout: Instant) -> Option<Result<usize, Error>> {
         self.data
             .try_lock_until(timeout)
-            .map(|data| crate::save_data_to_path(data.deref(), &self.file_path))
+            .map(|data| self.save_locked(data.deref(), false))
+    }
+
+    /// Like `try_save`, but reports a held lock as `Error::WouldBlock`
+    #[inline]
+    pub fn try_save_or_err(&self) -> Result<usize, Error> {
+        self.try_save().unwrap_or(Err(Error::WouldBlock))
     }
 
     #[inline]
     #[allow(clippy::missing_safety_doc)]
     pub unsafe fn force_unlock(&self) {
+        self.mark_unflushed();
         self.data.force_unlock()
     }
 
     #[inline]
     #[allow(clippy::missing_safety_doc)]
     pub unsafe fn force_unlock_fair(&self) {
+        self.mark_unflushed();
         self.data.force_unlock_fair()
     }
+
+    // A guard that is force-unlocked did not get to save, and may have modified the data
+    fn mark_unflushed(&self) {
+        self.dirty.store(true, Ordering::Relaxed);
+        self.needs_flush.store(true, Ordering::Relaxed);
+    }
+
+    fn save_locked(&self, data: &T, sync: bool) -> Result<usize, Error> {
+        if !self.dirty.load(Ordering::Relaxed) {
+            return Ok(0);
+        }
+
+        let len = crate::save_data_to_path::<Fmt, _>(data, &self.file_path, &self.options, sync)?;
+        self.dirty.store(false, Ordering::Relaxed);
+        self.needs_flush.store(false, Ordering::Relaxed);
+        Ok(len)
+    }
+
+    // Unlike `save_locked`, the data is compared to the file even if it is not dirty
+    fn save_if_changed_locked(&self, data: &T) -> Result<bool, Error> {
+        let changed = crate::save_data_if_changed::<Fmt, _>(data, &self.file_path, &self.options)?;
+        self.dirty.store(false, Ordering::Relaxed);
+        self.needs_flush.store(false, Ordering::Relaxed);
+        Ok(changed)
+    }
+
+    fn autosave_locked(&self, data: &T) {
+        if self.autosave && !thread::panicking() {
+            if let Err(err) = self.save_locked(data, false) {
+                *self.last_save_error.lock() = Some(err);
+            }
+        }
+    }
+}
+
+impl<T, Fmt> SaveLocked for Mutex<T, Fmt>
+where
+    T: Serialize + ?Sized,
+    Fmt: Format,
+{
+    unsafe fn save_locked_data(&self) -> Result<usize, Error> {
+        self.save_locked(&*self.data.data_ptr(), false)
+    }
+
+    unsafe fn autosave_locked_data(&self) {
+        self.autosave_locked(&*self.data.data_ptr());
+    }
+
+    fn set_dirty(&self) {
+        self.dirty.store(true, Ordering::Relaxed);
+    }
+}
+
+impl<T, Fmt> Sealed for Mutex<T, Fmt>
+where
+    T: Serialize + ?Sized,
+    Fmt: Format,
+{
+    fn write_tmp_file(&self) -> Result<Option<PendingSave<'_>>, Error> {
+        let data = self.data.lock();
+
+        if !self.dirty.load(Ordering::Relaxed) {
+            return Ok(None);
+        }
+
+        let len =
+            crate::write_tmp_file::<Fmt, _>(data.deref(), &self.file_path, &self.options, false)?;
+        self.dirty.store(false, Ordering::Relaxed);
+
+        Ok(Some(PendingSave::new(
+            &self.file_path,
+            &self.options,
+            len,
+            Some(&self.dirty),
+            Box::new(data),
+        )))
+    }
+}
+
+impl<T, Fmt> FromBuilder<T, Fmt> for Mutex<T, Fmt>
+where
+    T: Serialize + DeserializeOwned,
+    Fmt: Format,
+{
+    fn from_builder(builder: Builder<T, Fmt>, data: T) -> Self {
+        let mut lock = Self::new(data, builder.file_path);
+        lock.options = builder.options;
+        lock.autosave = builder.autosave;
+        lock
+    }
+}
+
+impl<T> Mutex<T, Json>
+where
+    T: Serialize + DeserializeOwned,
+{
+    /// Like `init`, but tolerates comments and trailing commas in the file. The data is still saved as strict JSON
+    #[inline]
+    pub fn init_lenient<P: Into<PathBuf>>(file_path: P) -> Result<Self, Error> {
+        let file_path = file_path.into();
+        let data = crate::load_data_from_path::<LenientJson, _>(&file_path, &Options::default())?;
+        Self::init_with(data, file_path)
+    }
 }
 
-impl<T> Debug for Mutex<T>
+impl<T> Mutex<T, JsonLines>
 where
-    T: Debug + Serialize + for<'de> Deserialize<'de> + ?Sized,
+    T: Serialize + DeserializeOwned,
+    for<'a> &'a T: IntoIterator,
+    for<'a> <&'a T as IntoIterator>::Item: Serialize,
+{
+    /// Only writes the items that are not in the file yet, instead of rewriting it. The whole file is rewritten if the
+    /// items written by the last `save_append` were changed or removed, the file was written by anything else since, or
+    /// it is compressed or encrypted. Every item is still serialized to detect changes. Appending skips backups, and is
+    /// not atomic
+    #[inline]
+    pub fn save_append(&self) -> Result<usize, Error> {
+        let data = self.data.lock();
+
+        if !self.dirty.load(Ordering::Relaxed) {
+            return Ok(0);
+        }
+
+        match crate::append::append_data_to_path(
+            data.deref(),
+            &self.file_path,
+            &self.options,
+            false,
+        )? {
+            Append::Appended(len) => {
+                self.dirty.store(false, Ordering::Relaxed);
+                self.needs_flush.store(false, Ordering::Relaxed);
+                Ok(len)
+            }
+            Append::Rewrite(lines) => {
+                let len = self.save_locked(data.deref(), false)?;
+                crate::append::rewritten(&self.options, lines, len);
+                Ok(len)
+            }
+        }
+    }
+}
+
+impl<T, Fmt> Debug for Mutex<T, Fmt>
+where
+    T: Debug + Serialize + DeserializeOwned,
 {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         self.data.fmt(f)
     }
 }
 
-pub struct MutexGuard<'a, T: ?Sized> {
-    mutex: &'a Mutex<T>,
+impl<T: ?Sized, Fmt> Mutex<T, Fmt> {
+    // `Drop` can not require `T: Serialize`, so the save function is picked when `with_save_on_drop` is called
+    fn save_before_drop(&mut self) {
+        let save = match self.drop_save.take() {
+            Some(save) if *self.dirty.get_mut() => save,
+            _ => return,
+        };
+
+        let data = self.data.get_mut() as *mut T as *const u8;
+
+        // The function was picked for the type the data had before any unsizing, which it still has
+        if let Err(err) = unsafe { save(data, &self.file_path, &self.options, false) } {
+            if let Some(handler) = &self.drop_error_handler {
+                handler(err);
+            }
+        }
+    }
+}
+
+impl<T: ?Sized, Fmt> Drop for Mutex<T, Fmt> {
+    #[inline]
+    fn drop(&mut self) {
+        self.save_before_drop();
+    }
+}
+
+pub struct MutexGuard<'a, T: ?Sized + Serialize, Fmt: Format = Json> {
+    mutex: &'a Mutex<T, Fmt>,
     guard: InnerMutexGuard<'a, T>,
 }
 
-impl<'a, T: ?Sized> MutexGuard<'a, T> {
+impl<'a, T: ?Sized + Serialize, Fmt: Format> MutexGuard<'a, T, Fmt> {
     #[inline]
-    pub fn mutex(s: &Self) -> &'a Mutex<T> {
+    pub fn mutex(s: &Self) -> &'a Mutex<T, Fmt> {
         s.mutex
     }
 
     #[inline]
     pub fn map<U: ?Sized, F>(s: Self, f: F) -> MappedMutexGuard<'a, U>
     where
+        Mutex<T, Fmt>: Sync,
         F: FnOnce(&mut T) -> &mut U,
     {
-        MappedMutexGuard(InnerMutexGuard::map(s.guard, f))
+        let mutex = s.mutex;
+        mutex.dirty.store(true, Ordering::Relaxed);
+
+        MappedMutexGuard {
+            mutex: Box::new(mutex),
+            guard: InnerMutexGuard::map(Self::into_inner(s), f),
+        }
     }
 
     #[inline]
     pub fn try_map<U: ?Sized, F>(s: Self, f: F) -> Result<MappedMutexGuard<'a, U>, Self>
     where
+        Mutex<T, Fmt>: Sync,
         F: FnOnce(&mut T) -> Option<&mut U>,
     {
-        InnerMutexGuard::try_map(s.guard, f).map_or_else(
+        let mutex = s.mutex;
+        mutex.dirty.store(true, Ordering::Relaxed);
+
+        InnerMutexGuard::try_map(Self::into_inner(s), f).map_or_else(
+            |g| Err(Self { mutex, guard: g }),
             |g| {
-                Err(Self {
-                    mutex: s.mutex,
+                Ok(MappedMutexGuard {
+                    mutex: Box::new(mutex),
                     guard: g,
                 })
             },
-            |g| Ok(MappedMutexGuard(g)),
         )
     }
 
@@ -208,34 +1228,72 @@ impl<'a, T: ?Sized> MutexGuard<'a, T> {
 
     #[inline]
     pub fn unlock_fair(s: Self) {
-        InnerMutexGuard::unlock_fair(s.guard);
+        Self::autosave(&s);
+        InnerMutexGuard::unlock_fair(Self::into_inner(s));
+    }
+
+    #[inline]
+    pub fn save_and_unlock_fair(s: Self) -> Result<usize, Error> {
+        let res = s.mutex.save_locked(s.guard.deref(), false);
+        InnerMutexGuard::unlock_fair(Self::into_inner(s));
+        res
     }
 
     #[inline]
     pub fn bump(s: &mut Self) {
         InnerMutexGuard::bump(&mut s.guard);
     }
+
+    #[inline]
+    pub fn save(s: &Self) -> Result<usize, Error> {
+        s.mutex.save_locked(s.guard.deref(), false)
+    }
+
+    /// Like `Mutex::save_if_changed`, while the guard is held
+    #[inline]
+    pub fn save_if_changed(s: &Self) -> Result<bool, Error> {
+        s.mutex.save_if_changed_locked(s.guard.deref())
+    }
+
+    fn autosave(s: &Self) {
+        s.mutex.autosave_locked(s.guard.deref());
+    }
+
+    // Moves the inner guard out without running `Drop`, which would trigger an autosave
+    fn into_inner(s: Self) -> InnerMutexGuard<'a, T> {
+        let s = ManuallyDrop::new(s);
+        unsafe { ptr::read(&s.guard) }
+    }
+}
+
+impl<T: ?Sized + Serialize, Fmt: Format> Drop for MutexGuard<'_, T, Fmt> {
+    #[inline]
+    fn drop(&mut self) {
+        Self::autosave(self);
+    }
 }
 
-impl<T> Debug for MutexGuard<'_, T>
+impl<T, Fmt> Debug for MutexGuard<'_, T, Fmt>
 where
-    T: Debug + ?Sized,
+    T: Debug + Serialize + ?Sized,
+    Fmt: Format,
 {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         self.guard.fmt(f)
     }
 }
 
-impl<T> Display for MutexGuard<'_, T>
+impl<T, Fmt> Display for MutexGuard<'_, T, Fmt>
 where
-    T: Display + ?Sized,
+    T: Display + Serialize + ?Sized,
+    Fmt: Format,
 {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         self.guard.fmt(f)
     }
 }
 
-impl<T: ?Sized + Serialize> Deref for MutexGuard<'_, T> {
+impl<T: ?Sized + Serialize, Fmt: Format> Deref for MutexGuard<'_, T, Fmt> {
     type Target = T;
 
     #[inline]
@@ -244,14 +1302,70 @@ impl<T: ?Sized + Serialize> Deref for MutexGuard<'_, T> {
     }
 }
 
-impl<T: ?Sized + Serialize> DerefMut for MutexGuard<'_, T> {
+impl<T: ?Sized + Serialize, Fmt: Format> DerefMut for MutexGuard<'_, T, Fmt> {
     #[inline]
     fn deref_mut(&mut self) -> &mut T {
+        self.mutex.dirty.store(true, Ordering::Relaxed);
         self.guard.deref_mut()
     }
 }
 
-pub struct MappedMutexGuard<'a, T: ?Sized>(InnerMappedMutexGuard<'a, T>);
+/// A `MutexGuard` that keeps the `Arc` of its lock alive instead of borrowing it
+pub struct ArcMutexGuard<T: ?Sized + Serialize + 'static, Fmt: Format + 'static = Json> {
+    // Declared first, so that it is dropped before the lock it borrows from
+    guard: MutexGuard<'static, T, Fmt>,
+    mutex: Arc<Mutex<T, Fmt>>,
+}
+
+impl<T: ?Sized + Serialize, Fmt: Format> ArcMutexGuard<T, Fmt> {
+    #[inline]
+    pub fn mutex(s: &Self) -> &Arc<Mutex<T, Fmt>> {
+        &s.mutex
+    }
+
+    /// Saves the data while the guard is held. `Mutex::save` would wait for this guard to be dropped
+    #[inline]
+    pub fn save(s: &Self) -> Result<usize, Error> {
+        MutexGuard::save(&s.guard)
+    }
+
+    /// Like `Mutex::save_if_changed`, while the guard is held
+    #[inline]
+    pub fn save_if_changed(s: &Self) -> Result<bool, Error> {
+        MutexGuard::save_if_changed(&s.guard)
+    }
+}
+
+impl<T, Fmt> Debug for ArcMutexGuard<T, Fmt>
+where
+    T: Debug + ?Sized + Serialize,
+    Fmt: Format,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        self.guard.fmt(f)
+    }
+}
+
+impl<T: ?Sized + Serialize, Fmt: Format> Deref for ArcMutexGuard<T, Fmt> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.guard.deref()
+    }
+}
+
+impl<T: ?Sized + Serialize, Fmt: Format> DerefMut for ArcMutexGuard<T, Fmt> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        self.guard.deref_mut()
+    }
+}
+
+pub struct MappedMutexGuard<'a, T: ?Sized> {
+    mutex: LockRef<'a>,
+    guard: InnerMappedMutexGuard<'a, T>,
+}
 
 impl<'a, T: ?Sized> MappedMutexGuard<'a, T> {
     #[inline]
@@ -259,7 +1373,13 @@ impl<'a, T: ?Sized> MappedMutexGuard<'a, T> {
     where
         F: FnOnce(&mut T) -> &mut U,
     {
-        MappedMutexGuard(InnerMappedMutexGuard::map(s.0, f))
+        s.mutex.set_dirty();
+        let (mutex, guard) = Self::into_parts(s);
+
+        MappedMutexGuard {
+            mutex,
+            guard: InnerMappedMutexGuard::map(guard, f),
+        }
     }
 
     #[inline]
@@ -267,13 +1387,38 @@ impl<'a, T: ?Sized> MappedMutexGuard<'a, T> {
     where
         F: FnOnce(&mut T) -> Option<&mut U>,
     {
-        InnerMappedMutexGuard::try_map(s.0, f)
-            .map_or_else(|g| Err(Self(g)), |g| Ok(MappedMutexGuard(g)))
+        s.mutex.set_dirty();
+        let (mutex, guard) = Self::into_parts(s);
+
+        match InnerMappedMutexGuard::try_map(guard, f) {
+            Ok(guard) => Ok(MappedMutexGuard { mutex, guard }),
+            Err(guard) => Err(Self { mutex, guard }),
+        }
     }
 
     #[inline]
     pub fn unlock_fair(s: Self) {
-        InnerMappedMutexGuard::unlock_fair(s.0);
+        unsafe { s.mutex.autosave_locked_data() };
+        InnerMappedMutexGuard::unlock_fair(Self::into_parts(s).1);
+    }
+
+    /// Saves the whole data behind the mutex, not only the mapped part
+    #[inline]
+    pub fn save(s: &Self) -> Result<usize, Error> {
+        unsafe { s.mutex.save_locked_data() }
+    }
+
+    // Moves the lock and the inner guard out without running `Drop`, which would trigger an autosave
+    fn into_parts(s: Self) -> (LockRef<'a>, InnerMappedMutexGuard<'a, T>) {
+        let s = ManuallyDrop::new(s);
+        unsafe { (ptr::read(&s.mutex), ptr::read(&s.guard)) }
+    }
+}
+
+impl<T: ?Sized> Drop for MappedMutexGuard<'_, T> {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe { self.mutex.autosave_locked_data() };
     }
 }
 
@@ -282,7 +1427,7 @@ where
     T: Debug + ?Sized,
 {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
-        self.0.fmt(f)
+        self.guard.fmt(f)
     }
 }
 
@@ -291,22 +1436,23 @@ where
     T: Display + ?Sized,
 {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
-        self.0.fmt(f)
+        self.guard.fmt(f)
     }
 }
 
-impl<T: ?Sized + Serialize> Deref for MappedMutexGuard<'_, T> {
+impl<T: ?Sized> Deref for MappedMutexGuard<'_, T> {
     type Target = T;
 
     #[inline]
     fn deref(&self) -> &Self::Target {
-        self.0.deref()
+        self.guard.deref()
     }
 }
 
-impl<T: ?Sized + Serialize> DerefMut for MappedMutexGuard<'_, T> {
+impl<T: ?Sized> DerefMut for MappedMutexGuard<'_, T> {
     #[inline]
     fn deref_mut(&mut self) -> &mut T {
-        self.0.deref_mut()
+        self.mutex.set_dirty();
+        self.guard.deref_mut()
     }
 }
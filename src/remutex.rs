@@ -1,53 +1,394 @@
+use crate::{
+    append::Append,
+    batch::{PendingSave, Sealed},
+    builder::FromBuilder,
+    lenient::LenientJson,
+    Builder, Error, Format, InitOutcome, Json, JsonLines, Options,
+};
 use parking_lot::{
     MappedReentrantMutexGuard as InnerMappedReentrantMutexGuard,
     ReentrantMutex as InnerReentrantMutex, ReentrantMutexGuard as InnerReentrantMutexGuard,
 };
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+#[cfg(feature = "notify")]
+use crate::WatchHandle;
 use std::{
     fmt::{Debug, Display, Formatter, Result as FmtResult},
-    fs::OpenOptions,
-    io::Error,
+    fs::{self, File, Metadata, OpenOptions},
+    io::{Result as IoResult, Write},
+    marker::PhantomData,
     ops::Deref,
     path::{Path, PathBuf},
-    time::{Duration, Instant},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
 };
 
-pub struct ReentrantMutex<T: ?Sized> {
+pub struct ReentrantMutex<T: ?Sized, Fmt = Json> {
     file_path: PathBuf,
+    options: Options,
+    file_lock: Option<File>,
+    format: PhantomData<Fmt>,
     data: InnerReentrantMutex<T>,
 }
 
-impl<T> ReentrantMutex<T>
+impl<T, Fmt> ReentrantMutex<T, Fmt> {
+    /// Creates the lock without touching the file, so it can be used in const contexts. The data is written on the
+    /// first save
+    #[inline]
+    pub const fn new_unchecked(data: T, file_path: PathBuf) -> Self {
+        Self {
+            data: parking_lot::const_reentrant_mutex(data),
+            file_path,
+            options: Options::new(),
+            file_lock: None,
+            format: PhantomData,
+        }
+    }
+}
+
+impl<T, Fmt> ReentrantMutex<T, Fmt>
 where
-    T: Serialize + for<'de> Deserialize<'de> + ?Sized,
+    T: Serialize + DeserializeOwned,
+    Fmt: Format,
 {
     #[inline]
     pub fn init<P: Into<PathBuf>>(file_path: P) -> Result<Self, Error> {
+        Builder::new(file_path).format::<Fmt>().open()
+    }
+
+    /// Like `init`, but does not rewrite the file after reading it. Changes can still be written with `save`
+    #[inline]
+    pub fn init_readonly<P: Into<PathBuf>>(file_path: P) -> Result<Self, Error> {
         let file_path = file_path.into();
+        let data = crate::load_data_from_path::<Fmt, _>(&file_path, &Options::default())?;
+        Ok(Self::new(data, file_path))
+    }
 
-        let data = {
-            let read = OpenOptions::new().read(true).open(&file_path)?;
-            serde_json::from_reader(read)?
+    /// Reads the data from an already opened file instead of opening `file_path`, which is only used for later saves.
+    /// The file is not rewritten
+    #[inline]
+    pub fn init_from_file<P: Into<PathBuf>>(file: File, file_path: P) -> Result<Self, Error> {
+        let file_path = file_path.into();
+        let data = crate::load_data_from_file::<Fmt, _>(file, &file_path, &Options::default())?;
+        Ok(Self::new(data, file_path))
+    }
+
+    /// Like `init`, but holds an advisory lock on `<path>.lock` until the lock is dropped, and fails with
+    /// `Error::FileLocked` if another process holds it. The lock file is never renamed, so saves replace the data file
+    /// while the lock is still held and a process that waits for it never reads a half-written or outdated file
+    #[inline]
+    pub fn init_exclusive<P: Into<PathBuf>>(file_path: P) -> Result<Self, Error> {
+        let file_path = file_path.into();
+        let file_lock = crate::lock_file(&file_path)?;
+        let mut lock = Self::init(file_path)?;
+        lock.file_lock = Some(file_lock);
+        Ok(lock)
+    }
+
+    #[cfg(feature = "encryption")]
+    #[inline]
+    pub fn init_encrypted<P: Into<PathBuf>>(file_path: P, key: [u8; 32]) -> Result<Self, Error> {
+        let file_path = file_path.into();
+
+        let options = Options {
+            cipher: Some(key),
+            ..Default::default()
         };
 
-        crate::save_data_to_path(&data, &file_path)?;
+        let data = crate::load_data_from_path::<Fmt, _>(&file_path, &options)?;
+        crate::save_data_to_path::<Fmt, _>(&data, &file_path, &options, false)?;
+        Ok(Self::new(data, file_path).with_cipher(key))
+    }
 
-        Ok(Self {
-            data: InnerReentrantMutex::new(data),
-            file_path,
-        })
+    #[inline]
+    pub fn init_arc<P: Into<PathBuf>>(file_path: P) -> Result<Arc<Self>, Error> {
+        Self::init(file_path).map(Arc::new)
+    }
+
+    #[inline]
+    pub fn init_with_arc<P: Into<PathBuf>>(data: T, file_path: P) -> Result<Arc<Self>, Error> {
+        Self::init_with(data, file_path).map(Arc::new)
     }
 
     #[inline]
     pub fn init_with<P: Into<PathBuf>>(data: T, file_path: P) -> Result<Self, Error> {
         let file_path = file_path.into();
+        crate::save_data_to_path::<Fmt, _>(&data, &file_path, &Options::default(), false)?;
+        Ok(Self::new(data, file_path))
+    }
+
+    /// Creates a lock that saves to and reloads from memory instead of a file, e.g. for tests. Its path is `:memory:`,
+    /// and the file options like backups or permissions have no effect
+    #[inline]
+    pub fn init_memory(data: T) -> Result<Self, Error> {
+        let mut lock = Self::new(data, PathBuf::from(crate::MEMORY_PATH));
+        lock.options.memory = Some(Arc::default());
+        crate::save_data_to_path::<Fmt, _>(
+            lock.data.get_mut().deref(),
+            &lock.file_path,
+            &lock.options,
+            false,
+        )?;
+        Ok(lock)
+    }
+
+    #[inline]
+    pub fn init_with_unsaved<P: Into<PathBuf>>(data: T, file_path: P) -> Self {
+        Self::new(data, file_path.into())
+    }
+
+    #[inline]
+    pub fn init_or_default<P: Into<PathBuf>>(file_path: P) -> Result<Self, Error>
+    where
+        T: Default,
+    {
+        Self::init_or_else(file_path, T::default)
+    }
+
+    /// Like `init_or_default`, but also tells whether the file was loaded or created
+    #[inline]
+    pub fn open<P: Into<PathBuf>>(file_path: P) -> Result<(Self, InitOutcome), Error>
+    where
+        T: Default,
+    {
+        Self::init_or_else_with_outcome(file_path, T::default)
+    }
+
+    /// Like `open`, but creates the file while opening it, so that a file created by another process in the meantime is
+    /// loaded instead of overwritten. A new or empty file is initialized with `T::default()`
+    #[inline]
+    pub fn open_or_create<P: Into<PathBuf>>(file_path: P) -> Result<(Self, InitOutcome), Error>
+    where
+        T: Default,
+    {
+        let file_path = file_path.into();
+        let (data, outcome) =
+            crate::load_or_create::<Fmt, _, _>(&file_path, &Options::default(), T::default)?;
+        Self::init_with(data, file_path).map(|lock| (lock, outcome))
+    }
+
+    #[inline]
+    pub fn init_or_else<P, F>(file_path: P, f: F) -> Result<Self, Error>
+    where
+        P: Into<PathBuf>,
+        F: FnOnce() -> T,
+    {
+        Self::init_or_else_with_outcome(file_path, f).map(|(lock, _)| lock)
+    }
+
+    fn init_or_else_with_outcome<P, F>(file_path: P, f: F) -> Result<(Self, InitOutcome), Error>
+    where
+        P: Into<PathBuf>,
+        F: FnOnce() -> T,
+    {
+        let file_path = file_path.into();
+
+        let (data, outcome) =
+            match crate::load_data_from_path::<Fmt, _>(&file_path, &Options::default()) {
+                Ok(data) => (data, InitOutcome::Loaded),
+                Err(err) if err.is_missing_file() => (f(), InitOutcome::Created),
+                Err(err) => return Err(err),
+            };
+
+        Self::init_with(data, file_path).map(|lock| (lock, outcome))
+    }
+
+    /// Loads the first of `paths` that exists, and saves to it from then on. If none exists, the error of the last one
+    /// is returned
+    #[inline]
+    pub fn init_from_paths<I>(paths: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = PathBuf>,
+    {
+        crate::init_from_paths(paths, Self::init)
+    }
+
+    /// Like `init`, but if the file exists and can't be loaded, e.g. because it is corrupt, loads the newest backup
+    /// written with `with_backups` that can be, and saves it to `file_path`. Fails with the error of the file if none
+    /// can
+    #[inline]
+    pub fn init_with_recovery<P: Into<PathBuf>>(file_path: P) -> Result<Self, Error> {
+        let file_path = file_path.into();
+        let data = crate::backup::load_with_recovery::<Fmt, _>(&file_path, &Options::default())?;
+        Self::init_with(data, file_path)
+    }
+
+    #[inline]
+    pub fn init_with_migration<P, F>(file_path: P, migrate: F) -> Result<Self, Error>
+    where
+        P: Into<PathBuf>,
+        F: FnOnce(Value) -> Value,
+    {
+        let file_path = file_path.into();
+        let value = crate::load_data_from_path::<Fmt, Value>(&file_path, &Options::default())?;
+        let data = serde_json::from_value(migrate(value))
+            .map_err(|err| Error::from(err).loading(&file_path))?;
+        Self::init_with(data, file_path)
+    }
 
-        crate::save_data_to_path(&data, &file_path)?;
+    /// Loads a file written by `save_projection`, and builds the full data from the saved part with `build`. The file
+    /// is left as it is
+    #[inline]
+    pub fn init_projection<P, S, F>(file_path: P, build: F) -> Result<Self, Error>
+    where
+        P: Into<PathBuf>,
+        S: DeserializeOwned,
+        F: FnOnce(S) -> T,
+    {
+        let file_path = file_path.into();
+        let part = crate::load_data_from_path::<Fmt, S>(&file_path, &Options::default())?;
+        Ok(Self::new(build(part), file_path))
+    }
 
-        Ok(Self {
+    #[inline]
+    fn new(data: T, file_path: PathBuf) -> Self {
+        Self {
             data: InnerReentrantMutex::new(data),
             file_path,
-        })
+            options: Options::default(),
+            file_lock: None,
+            format: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub fn with_backups(mut self, count: usize) -> Self {
+        self.options.backups = count;
+        self
+    }
+
+    /// Retries writing the file up to `count` times when it fails with an `Interrupted`, `WouldBlock` or `TimedOut` IO
+    /// error. The first retry waits for `backoff`, and every further one twice as long as the one before, up to a
+    /// minute
+    #[inline]
+    pub fn with_retries(mut self, count: usize, backoff: Duration) -> Self {
+        self.options.retries = count;
+        self.options.retry_backoff = backoff;
+        self
+    }
+
+    /// Opens the file with `open_options` to save and reload it, e.g. with `custom_flags` to not follow symlinks. The
+    /// read, write and create flags are set by jsave. Use `Builder::open_options` to also apply them to the first load
+    #[inline]
+    pub fn with_open_options(mut self, open_options: OpenOptions) -> Self {
+        self.options.open_options = Some(open_options);
+        self
+    }
+
+    #[inline]
+    pub fn with_indent<S: Into<String>>(mut self, indent: S) -> Self {
+        self.options.pretty = true;
+        self.options.indent = Some(indent.into());
+        self
+    }
+
+    /// Writes the keys of every map in sorted order, so the file only changes when the data does. The data is converted
+    /// to a `serde_json::Value` first, so saving fails with `Error::UnsupportedFormat` on formats other than JSON, JSON
+    /// Lines and JSON5
+    #[inline]
+    pub fn with_sorted_keys(mut self, sorted: bool) -> Self {
+        self.options.sort_keys = sorted;
+        self
+    }
+
+    /// Ends the file with a newline, as many text tools expect. Output that already ends with one is left as it is.
+    /// Saving fails with `Error::UnsupportedFormat` on binary formats
+    #[inline]
+    pub fn with_trailing_newline(mut self, trailing_newline: bool) -> Self {
+        self.options.trailing_newline = trailing_newline;
+        self
+    }
+
+    /// Writes the same bytes for the same data, e.g. to sign the file: keys are sorted like with `with_sorted_keys`,
+    /// and the output is never pretty-printed. JSON numbers are written in their shortest exact form, while NaN and
+    /// infinite floats are written as `null`. Saving fails with `Error::UnsupportedFormat` on formats other than JSON,
+    /// JSON Lines and JSON5
+    #[inline]
+    pub fn with_canonical(mut self, canonical: bool) -> Self {
+        self.options.canonical = canonical;
+        self
+    }
+
+    /// Writes the temporary file into `dir` instead of next to the file. If `dir` is on another filesystem, the file
+    /// can't be renamed into place and is copied over instead, so saves are no longer atomic
+    #[inline]
+    pub fn with_temp_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.options.temp_dir = Some(dir.into());
+        self
+    }
+
+    /// Calls `f` with the path and the number of bytes written after every successful save, whichever method triggered
+    /// it
+    #[inline]
+    pub fn with_on_save<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&Path, usize) + Send + Sync + 'static,
+    {
+        self.options.on_save = Some(Arc::new(f));
+        self
+    }
+
+    /// Applies `f` to a copy of the data right before every save, e.g. to leave secrets out of the file. The data in
+    /// the lock is not changed, and `save_if_changed` compares the file to the data as changed by `f`
+    #[inline]
+    pub fn with_pre_save<F>(mut self, f: F) -> Self
+    where
+        T: Clone + 'static,
+        Fmt: 'static,
+        F: Fn(&mut T) + Send + Sync + 'static,
+    {
+        self.options.pre_save = Some(crate::pre_save_fn::<Fmt, T, F>(f));
+        self
+    }
+
+    /// Reads every save back before it replaces the file, and fails with `Error::RoundtripFailed` if the data does not
+    /// deserialize to the same value. Both are compared as `serde_json::Value`
+    #[inline]
+    pub fn with_verify_roundtrip(mut self, verify: bool) -> Self {
+        self.options.verify = if verify {
+            Some(crate::load_value::<Fmt, T>)
+        } else {
+            None
+        };
+        self
+    }
+
+    #[inline]
+    pub fn with_mode(mut self, mode: u32) -> Self {
+        self.options.mode = Some(mode);
+        self
+    }
+
+    #[inline]
+    pub fn with_create_dirs(mut self, create_dirs: bool) -> Self {
+        self.options.create_dirs = create_dirs;
+        self
+    }
+
+    #[cfg(feature = "encryption")]
+    #[inline]
+    pub fn with_cipher(mut self, key: [u8; 32]) -> Self {
+        self.options.cipher = Some(key);
+        self
+    }
+
+    /// Stores a CRC32 checksum of the file in `<path>.sum` on every save, and verifies it when the file is loaded
+    /// again. A missing checksum fails loading with `Error::ChecksumMissing`, a different one with
+    /// `Error::ChecksumMismatch`. Set it with `Builder::checksum` for the first load to be verified as well
+    #[cfg(feature = "checksum")]
+    #[inline]
+    pub fn with_checksum(mut self, checksum: bool) -> Self {
+        self.options.checksum = checksum;
+        self
+    }
+
+    #[cfg(feature = "compression")]
+    #[inline]
+    pub fn with_compression(mut self, level: u32) -> Self {
+        self.options.compression = Some(level);
+        self
     }
 
     #[inline]
@@ -55,11 +396,148 @@ where
         self.data.into_inner()
     }
 
+    /// Clones the data out of the lock, without saving it
+    #[inline]
+    pub fn snapshot(&self) -> T
+    where
+        T: Clone,
+    {
+        self.data.lock().clone()
+    }
+
+    /// Same as `snapshot`
+    #[inline]
+    pub fn get(&self) -> T
+    where
+        T: Clone,
+    {
+        self.snapshot()
+    }
+
+    /// Saves a copy of the data to `file_path`, and returns an independent lock of it with the same options
+    #[inline]
+    pub fn clone_to<P: Into<PathBuf>>(&self, file_path: P) -> Result<Self, Error>
+    where
+        T: Clone,
+    {
+        let file_path = file_path.into();
+        let data = self.snapshot();
+        let options = self.options.without_memory();
+        crate::save_data_to_path::<Fmt, _>(&data, &file_path, &options, false)?;
+
+        let mut lock = Self::new(data, file_path);
+        lock.options = options;
+        Ok(lock)
+    }
+
+    /// Discards the changes made since the last save by reloading the file. Same as `reload`
+    #[inline]
+    pub fn revert(&mut self) -> Result<(), Error> {
+        self.reload()
+    }
+
+    // Guards held by the current thread may still borrow the data, so replacing it needs `&mut self`
+    #[inline]
+    pub fn reload(&mut self) -> Result<(), Error> {
+        *self.data.get_mut() =
+            crate::load_data_from_path::<Fmt, _>(&self.file_path, &self.options)?;
+        Ok(())
+    }
+
+    /// Like `reload`, but passes the loaded data to `merge` to move it into the current data, e.g. to reuse its
+    /// allocations
+    #[inline]
+    pub fn reload_into<F>(&mut self, merge: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut T, T),
+    {
+        let loaded = crate::load_data_from_path::<Fmt, _>(&self.file_path, &self.options)?;
+        merge(self.data.get_mut(), loaded);
+        Ok(())
+    }
+
+    /// Calls `f` in a background thread whenever the file is modified on disk, e.g. to `reload` it. Bursts of
+    /// modifications are reported once. Saves made through this lock are reported too
+    #[cfg(feature = "notify")]
+    #[inline]
+    pub fn watch<F>(&self, f: F) -> Result<WatchHandle, Error>
+    where
+        F: FnMut() + Send + 'static,
+    {
+        if self.is_memory() {
+            return Err(Error::Io {
+                op: crate::IoOp::Watch,
+                source: crate::no_file_error(),
+            });
+        }
+
+        WatchHandle::spawn(&self.file_path, f)
+    }
+}
+
+impl<T, Fmt> ReentrantMutex<T, Fmt>
+where
+    T: Serialize + ?Sized,
+    Fmt: Format,
+{
+    /// The underlying parking_lot lock. Changes made through it are only persisted by calling `save`
+    #[inline]
+    pub fn inner(&self) -> &InnerReentrantMutex<T> {
+        &self.data
+    }
+
     #[inline]
     pub fn path(&self) -> &Path {
         &self.file_path
     }
 
+    /// Whether the lock was created with `init_memory`, in which case `path` is only the placeholder `:memory:`, and
+    /// `file_metadata` and `watch` fail
+    #[inline]
+    pub fn is_memory(&self) -> bool {
+        self.options.memory.is_some()
+    }
+
+    /// When the data was last written to the file by this lock, without locking it. Saving a part of the data with
+    /// `save_projection` does not count
+    #[inline]
+    pub fn last_saved(&self) -> Option<SystemTime> {
+        self.options.last_saved.get()
+    }
+
+    /// Metadata of the file on disk, e.g. when it was last saved. The data is not locked
+    #[inline]
+    pub fn file_metadata(&self) -> IoResult<Metadata> {
+        if self.is_memory() {
+            return Err(crate::no_file_error());
+        }
+
+        fs::metadata(&self.file_path)
+    }
+
+    /// The lock taken by `init_exclusive` stays on the lock file of the original path
+    #[inline]
+    pub fn set_path<P: Into<PathBuf>>(&mut self, file_path: P) {
+        self.file_path = file_path.into();
+    }
+
+    /// Like `set_path`, but saves the data to the new path right away
+    #[inline]
+    pub fn set_path_and_save<P: Into<PathBuf>>(&mut self, file_path: P) -> Result<usize, Error> {
+        self.set_path(file_path);
+        self.save()
+    }
+
+    #[inline]
+    pub fn is_pretty(&self) -> bool {
+        self.options.pretty
+    }
+
+    #[inline]
+    pub fn set_pretty(&mut self, pretty: bool) {
+        self.options.pretty = pretty;
+    }
+
     #[inline]
     pub fn get_mut(&mut self) -> &mut T {
         self.data.get_mut()
@@ -81,7 +559,7 @@ where
     }
 
     #[inline]
-    pub fn lock(&self) -> ReentrantMutexGuard<T> {
+    pub fn lock(&self) -> ReentrantMutexGuard<'_, T, Fmt> {
         ReentrantMutexGuard {
             remutex: self,
             guard: self.data.lock(),
@@ -89,7 +567,7 @@ where
     }
 
     #[inline]
-    pub fn try_lock(&self) -> Option<ReentrantMutexGuard<T>> {
+    pub fn try_lock(&self) -> Option<ReentrantMutexGuard<'_, T, Fmt>> {
         self.data.try_lock().map(|g| ReentrantMutexGuard {
             remutex: self,
             guard: g,
@@ -97,7 +575,7 @@ where
     }
 
     #[inline]
-    pub fn try_lock_for(&self, timeout: Duration) -> Option<ReentrantMutexGuard<T>> {
+    pub fn try_lock_for(&self, timeout: Duration) -> Option<ReentrantMutexGuard<'_, T, Fmt>> {
         self.data
             .try_lock_for(timeout)
             .map(|g| ReentrantMutexGuard {
@@ -107,7 +585,7 @@ where
     }
 
     #[inline]
-    pub fn try_lock_until(&self, timeout: Instant) -> Option<ReentrantMutexGuard<T>> {
+    pub fn try_lock_until(&self, timeout: Instant) -> Option<ReentrantMutexGuard<'_, T, Fmt>> {
         self.data
             .try_lock_until(timeout)
             .map(|g| ReentrantMutexGuard {
@@ -117,30 +595,113 @@ where
     }
 
     #[inline]
-    pub fn save(&self) -> Result<(), Error> {
+    pub fn save(&self) -> Result<usize, Error> {
         let data = self.data.lock();
-        crate::save_data_to_path(data.deref(), &self.file_path)
+        crate::save_data_to_path::<Fmt, _>(data.deref(), &self.file_path, &self.options, false)
     }
 
     #[inline]
-    pub fn try_save(&self) -> Option<Result<(), Error>> {
-        self.data
-            .try_lock()
-            .map(|data| crate::save_data_to_path(data.deref(), &self.file_path))
+    pub fn save_durable(&self) -> Result<usize, Error> {
+        let data = self.data.lock();
+        crate::save_data_to_path::<Fmt, _>(data.deref(), &self.file_path, &self.options, true)
+    }
+
+    #[inline]
+    pub fn save_as<P: AsRef<Path>>(&self, path: P) -> Result<usize, Error> {
+        let data = self.data.lock();
+        crate::save_data_to_path::<Fmt, _>(
+            data.deref(),
+            path.as_ref(),
+            &self.options.without_memory(),
+            false,
+        )
+    }
+
+    /// Saves like `save`, even if the data was not modified, but only replaces the file if what was written differs
+    /// from its current content, and returns whether it did. Encrypted files never match, as every save uses a new
+    /// nonce
+    #[inline]
+    pub fn save_if_changed(&self) -> Result<bool, Error> {
+        let data = self.data.lock();
+        crate::save_data_if_changed::<Fmt, _>(data.deref(), &self.file_path, &self.options)
+    }
+
+    /// Runs `f` on the locked data and saves it before unlocking. The data is shared with the other guards of the
+    /// current thread, so it can only be changed through interior mutability
+    #[inline]
+    pub fn update<F, R>(&self, f: F) -> Result<R, Error>
+    where
+        F: FnOnce(&T) -> R,
+    {
+        self.update_locked(&self.data.lock(), f)
     }
 
     #[inline]
-    pub fn try_save_for(&self, timeout: Duration) -> Option<Result<(), Error>> {
+    pub fn try_update_for<F, R>(&self, timeout: Duration, f: F) -> Option<Result<R, Error>>
+    where
+        F: FnOnce(&T) -> R,
+    {
         self.data
             .try_lock_for(timeout)
-            .map(|data| crate::save_data_to_path(data.deref(), &self.file_path))
+            .map(|data| self.update_locked(&data, f))
+    }
+
+    fn update_locked<F, R>(&self, data: &T, f: F) -> Result<R, Error>
+    where
+        F: FnOnce(&T) -> R,
+    {
+        let res = f(data);
+        crate::save_data_to_path::<Fmt, _>(data, &self.file_path, &self.options, false)?;
+        Ok(res)
     }
 
+    /// Saves only the part of the data returned by `f`, e.g. to leave out runtime caches, which `init_projection` loads
+    /// back. `save` still writes all of the data
     #[inline]
-    pub fn try_save_until(&self, timeout: Instant) -> Option<Result<(), Error>> {
-        self.data
-            .try_lock_until(timeout)
-            .map(|data| crate::save_data_to_path(data.deref(), &self.file_path))
+    pub fn save_projection<S, F>(&self, f: F) -> Result<usize, Error>
+    where
+        S: Serialize + ?Sized,
+        F: FnOnce(&T) -> &S,
+    {
+        let data = self.data.lock();
+        let options = self.options.without_typed_hooks();
+        let len =
+            crate::save_data_to_path::<Fmt, _>(f(data.deref()), &self.file_path, &options, false)?;
+        self.options.persisted_lines.forget();
+        Ok(len)
+    }
+
+    #[inline]
+    pub fn save_to_writer<W: Write>(&self, writer: W) -> Result<(), Error> {
+        let data = self.data.lock();
+        crate::serialize_to_writer::<Fmt, _, _>(writer, data.deref(), &self.options)
+    }
+
+    #[inline]
+    pub fn try_save(&self) -> Option<Result<usize, Error>> {
+        self.data.try_lock().map(|data| {
+            crate::save_data_to_path::<Fmt, _>(data.deref(), &self.file_path, &self.options, false)
+        })
+    }
+
+    #[inline]
+    pub fn try_save_for(&self, timeout: Duration) -> Option<Result<usize, Error>> {
+        self.data.try_lock_for(timeout).map(|data| {
+            crate::save_data_to_path::<Fmt, _>(data.deref(), &self.file_path, &self.options, false)
+        })
+    }
+
+    #[inline]
+    pub fn try_save_until(&self, timeout: Instant) -> Option<Result<usize, Error>> {
+        self.data.try_lock_until(timeout).map(|data| {
+            crate::save_data_to_path::<Fmt, _>(data.deref(), &self.file_path, &self.options, false)
+        })
+    }
+
+    /// Like `try_save`, but reports a held lock as `Error::WouldBlock`
+    #[inline]
+    pub fn try_save_or_err(&self) -> Result<usize, Error> {
+        self.try_save().unwrap_or(Err(Error::WouldBlock))
     }
 
     #[inline]
@@ -156,23 +717,102 @@ where
     }
 }
 
-impl<T> Debug for ReentrantMutex<T>
+impl<T, Fmt> Sealed for ReentrantMutex<T, Fmt>
+where
+    T: Serialize + ?Sized,
+    Fmt: Format,
+{
+    fn write_tmp_file(&self) -> Result<Option<PendingSave<'_>>, Error> {
+        let data = self.data.lock();
+        let len =
+            crate::write_tmp_file::<Fmt, _>(data.deref(), &self.file_path, &self.options, false)?;
+        Ok(Some(PendingSave::new(
+            &self.file_path,
+            &self.options,
+            len,
+            None,
+            Box::new(data),
+        )))
+    }
+}
+
+impl<T, Fmt> FromBuilder<T, Fmt> for ReentrantMutex<T, Fmt>
+where
+    T: Serialize + DeserializeOwned,
+    Fmt: Format,
+{
+    fn from_builder(builder: Builder<T, Fmt>, data: T) -> Self {
+        let mut lock = Self::new(data, builder.file_path);
+        lock.options = builder.options;
+        lock
+    }
+}
+
+impl<T> ReentrantMutex<T, Json>
 where
-    T: Debug + Serialize + for<'de> Deserialize<'de> + ?Sized,
+    T: Serialize + DeserializeOwned,
+{
+    /// Like `init`, but tolerates comments and trailing commas in the file. The data is still saved as strict JSON
+    #[inline]
+    pub fn init_lenient<P: Into<PathBuf>>(file_path: P) -> Result<Self, Error> {
+        let file_path = file_path.into();
+        let data = crate::load_data_from_path::<LenientJson, _>(&file_path, &Options::default())?;
+        Self::init_with(data, file_path)
+    }
+}
+
+impl<T> ReentrantMutex<T, JsonLines>
+where
+    T: Serialize + DeserializeOwned,
+    for<'a> &'a T: IntoIterator,
+    for<'a> <&'a T as IntoIterator>::Item: Serialize,
+{
+    /// Only writes the items that are not in the file yet, instead of rewriting it. The whole file is rewritten if the
+    /// items written by the last `save_append` were changed or removed, the file was written by anything else since, or
+    /// it is compressed or encrypted. Every item is still serialized to detect changes. Appending skips backups, and is
+    /// not atomic
+    #[inline]
+    pub fn save_append(&self) -> Result<usize, Error> {
+        let data = self.data.lock();
+
+        match crate::append::append_data_to_path(
+            data.deref(),
+            &self.file_path,
+            &self.options,
+            false,
+        )? {
+            Append::Appended(len) => Ok(len),
+            Append::Rewrite(lines) => {
+                let len = crate::save_data_to_path::<JsonLines, _>(
+                    data.deref(),
+                    &self.file_path,
+                    &self.options,
+                    false,
+                )?;
+                crate::append::rewritten(&self.options, lines, len);
+                Ok(len)
+            }
+        }
+    }
+}
+
+impl<T, Fmt> Debug for ReentrantMutex<T, Fmt>
+where
+    T: Debug + Serialize + DeserializeOwned,
 {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         self.data.fmt(f)
     }
 }
 
-pub struct ReentrantMutexGuard<'a, T: ?Sized> {
-    remutex: &'a ReentrantMutex<T>,
+pub struct ReentrantMutexGuard<'a, T: ?Sized, Fmt = Json> {
+    remutex: &'a ReentrantMutex<T, Fmt>,
     guard: InnerReentrantMutexGuard<'a, T>,
 }
 
-impl<'a, T: ?Sized> ReentrantMutexGuard<'a, T> {
+impl<'a, T: ?Sized, Fmt> ReentrantMutexGuard<'a, T, Fmt> {
     #[inline]
-    pub fn remutex(s: &Self) -> &'a ReentrantMutex<T> {
+    pub fn remutex(s: &Self) -> &'a ReentrantMutex<T, Fmt> {
         s.remutex
     }
 
@@ -187,7 +827,7 @@ impl<'a, T: ?Sized> ReentrantMutexGuard<'a, T> {
     #[inline]
     pub fn try_map<U: ?Sized, F>(s: Self, f: F) -> Result<MappedReentrantMutexGuard<'a, U>, Self>
     where
-        F: FnOnce(&mut T) -> Option<&mut U>,
+        F: FnOnce(&T) -> Option<&U>,
     {
         InnerReentrantMutexGuard::try_map(s.guard, f).map_or_else(
             |g| {
@@ -227,7 +867,7 @@ impl<'a, T: ?Sized> ReentrantMutexGuard<'a, T> {
     }
 }
 
-impl<T> Debug for ReentrantMutexGuard<'_, T>
+impl<T, Fmt> Debug for ReentrantMutexGuard<'_, T, Fmt>
 where
     T: Debug + ?Sized,
 {
@@ -236,7 +876,7 @@ where
     }
 }
 
-impl<T> Display for ReentrantMutexGuard<'_, T>
+impl<T, Fmt> Display for ReentrantMutexGuard<'_, T, Fmt>
 where
     T: Display + ?Sized,
 {
@@ -245,7 +885,7 @@ where
     }
 }
 
-impl<T: ?Sized + Serialize> Deref for ReentrantMutexGuard<'_, T> {
+impl<T: ?Sized + Serialize, Fmt> Deref for ReentrantMutexGuard<'_, T, Fmt> {
     type Target = T;
 
     #[inline]
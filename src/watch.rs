@@ -0,0 +1,89 @@
+use crate::{error::IoContext, Error, IoOp};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::{
+    path::Path,
+    sync::mpsc::{self, RecvTimeoutError},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+// Editors and `save` itself touch the file several times in a row, which should only be reported once
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Handle of a file watcher. The watcher is stopped when the handle is dropped, so it must be kept alive for as long as
+/// changes should be reported
+#[must_use = "the watcher is stopped when the handle is dropped"]
+pub struct WatchHandle {
+    watcher: Option<RecommendedWatcher>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl WatchHandle {
+    pub(crate) fn spawn<F>(path: &Path, mut f: F) -> Result<Self, Error>
+    where
+        F: FnMut() + Send + 'static,
+    {
+        // The file is replaced on every save, so its directory is watched instead of the file itself
+        let file_name = path.file_name().ok_or_else(|| Error::Io {
+            op: IoOp::Watch,
+            source: std::io::ErrorKind::InvalidInput.into(),
+        })?;
+
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        }
+        .canonicalize()
+        .context(IoOp::Watch)?;
+
+        let file_path = dir.join(file_name);
+
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(tx)?;
+        watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+
+        let thread = thread::spawn(move || {
+            while let Ok(event) = rx.recv() {
+                if !is_modified(event, &file_path) {
+                    continue;
+                }
+
+                loop {
+                    match rx.recv_timeout(DEBOUNCE) {
+                        Ok(_) => {}
+                        Err(RecvTimeoutError::Timeout) => break,
+                        Err(RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+
+                f();
+            }
+        });
+
+        Ok(Self {
+            watcher: Some(watcher),
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for WatchHandle {
+    fn drop(&mut self) {
+        // Dropping the watcher closes the event channel, which ends the thread
+        self.watcher.take();
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn is_modified(event: Result<Event, notify::Error>, file_path: &Path) -> bool {
+    match event {
+        Ok(event) => {
+            matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+                && event.paths.iter().any(|path| path == file_path)
+        }
+        Err(_) => false,
+    }
+}
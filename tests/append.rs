@@ -0,0 +1,68 @@
+use jsave::{JsonLines, Mutex, RwLock};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+fn tmp_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("jsave-{}-{}.jsonl", name, std::process::id()))
+}
+
+fn remove(path: &Path) {
+    let _ = fs::remove_file(path);
+    let _ = fs::remove_file(path.with_extension("jsonl.sum"));
+}
+
+#[test]
+fn save_append_only_writes_new_items() {
+    let path = tmp_path("append-new");
+    let lock: Mutex<Vec<u32>, JsonLines> = Mutex::init_with(vec![1, 2], &path).unwrap();
+
+    lock.lock().push(3);
+    lock.save_append().unwrap();
+    lock.lock().push(4);
+    assert_eq!(lock.save_append().unwrap(), 2);
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), "1\n2\n3\n4\n");
+    assert_eq!(
+        *Mutex::<Vec<u32>, JsonLines>::init(&path).unwrap().lock(),
+        [1, 2, 3, 4]
+    );
+    remove(&path);
+}
+
+#[test]
+fn save_append_rewrites_changed_items() {
+    let path = tmp_path("append-changed");
+    let lock: RwLock<Vec<u32>, JsonLines> = RwLock::init_with(vec![1, 2], &path).unwrap();
+    lock.write().push(3);
+    lock.save_append().unwrap();
+
+    {
+        let mut data = lock.write();
+        data[0] = 100;
+        data.push(4);
+    }
+
+    lock.save_append().unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "100\n2\n3\n4\n");
+
+    lock.write().remove(1);
+    lock.save_append().unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "100\n3\n4\n");
+    remove(&path);
+}
+
+#[test]
+fn save_append_rewrites_after_other_writes() {
+    let path = tmp_path("append-other");
+    let lock: Mutex<Vec<u32>, JsonLines> = Mutex::init_with(vec![1], &path).unwrap();
+    lock.lock().push(2);
+    lock.save_append().unwrap();
+
+    fs::write(&path, "7\n").unwrap();
+    lock.lock().push(3);
+    lock.save_append().unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "1\n2\n3\n");
+    remove(&path);
+}
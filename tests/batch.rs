@@ -0,0 +1,61 @@
+use jsave::{save_all, Mutex, RwLock};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+};
+
+fn tmp_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("jsave-{}-{}.json", name, std::process::id()))
+}
+
+fn remove(path: &Path) {
+    let _ = fs::remove_file(path);
+    let _ = fs::remove_file(path.with_extension("json.sum"));
+}
+
+#[test]
+fn save_all_and_save_do_not_share_the_temporary_file() {
+    let path = tmp_path("batch-race");
+    let lock: Arc<RwLock<Vec<u32>>> = Arc::new(RwLock::init_with(vec![0; 64], &path).unwrap());
+
+    let threads = (0..2)
+        .map(|idx| {
+            let lock = Arc::clone(&lock);
+
+            thread::spawn(move || {
+                for round in 0..200 {
+                    lock.write()[round % 64] = round as u32;
+
+                    if idx == 0 {
+                        save_all(&[&*lock]).unwrap();
+                    } else {
+                        lock.save().unwrap();
+                    }
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for _ in 0..200 {
+        let content = fs::read(&path).unwrap();
+        serde_json::from_slice::<Vec<u32>>(&content).unwrap();
+    }
+
+    threads
+        .into_iter()
+        .for_each(|thread| thread.join().unwrap());
+    remove(&path);
+}
+
+#[test]
+fn save_all_accepts_a_lock_twice() {
+    let path = tmp_path("batch-twice");
+    let lock: Mutex<u32> = Mutex::init_with(1, &path).unwrap();
+    *lock.lock() = 2;
+
+    save_all(&[&lock, &lock]).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "2");
+    remove(&path);
+}
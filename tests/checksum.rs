@@ -0,0 +1,79 @@
+use jsave::{Builder, Error, RwLock};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+fn tmp_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!(
+        "jsave-checksum-{}-{}.json",
+        name,
+        std::process::id()
+    ))
+}
+
+fn sum_path(path: &Path) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".sum");
+    PathBuf::from(path)
+}
+
+fn open(path: &Path) -> Result<RwLock<Vec<u32>>, Error> {
+    Builder::new(path).checksum(true).open()
+}
+
+#[test]
+fn checksum_is_only_written_when_enabled() {
+    let path = tmp_path("enabled");
+
+    let lock: RwLock<Vec<u32>> = RwLock::init_with(vec![1], &path).unwrap();
+    lock.mark_dirty();
+    lock.save().unwrap();
+    assert!(!sum_path(&path).exists());
+
+    let lock = lock.with_checksum(true);
+    lock.mark_dirty();
+    lock.save().unwrap();
+    assert!(sum_path(&path).exists());
+    assert_eq!(*open(&path).unwrap().read(), [1]);
+
+    let _ = fs::remove_file(&path);
+    let _ = fs::remove_file(sum_path(&path));
+}
+
+#[test]
+fn missing_checksum_is_reported() {
+    let path = tmp_path("missing");
+    fs::write(&path, "[1]").unwrap();
+
+    let err = open(&path).unwrap_err();
+    assert!(matches!(err.root(), Error::ChecksumMissing));
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn interrupted_save_leaves_a_matching_checksum() {
+    let path = tmp_path("interrupted");
+    let lock: RwLock<Vec<u32>> = RwLock::init_with(vec![1], &path)
+        .unwrap()
+        .with_checksum(true);
+    lock.mark_dirty();
+    lock.save().unwrap();
+    let old = fs::read(&path).unwrap();
+
+    lock.write().push(2);
+    lock.save().unwrap();
+    drop(lock);
+
+    // The checksum is moved in place before the data, so a crash in between leaves the old file with the new checksum
+    fs::write(&path, &old).unwrap();
+    assert_eq!(*open(&path).unwrap().read(), [1]);
+
+    fs::write(&path, "[3]").unwrap();
+    let err = open(&path).unwrap_err();
+    assert!(matches!(err.root(), Error::ChecksumMismatch));
+
+    let _ = fs::remove_file(&path);
+    let _ = fs::remove_file(sum_path(&path));
+}
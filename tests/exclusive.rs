@@ -0,0 +1,42 @@
+use jsave::{Error, Mutex};
+use std::{env, fs, path::PathBuf, thread};
+
+const THREADS: u32 = 4;
+const ROUNDS: u32 = 50;
+
+#[test]
+fn exclusive_saves_lose_no_updates() {
+    let path = env::temp_dir().join(format!("jsave-exclusive-{}.json", std::process::id()));
+    drop(Mutex::<u32>::init_with(0, &path).unwrap());
+
+    let threads = (0..THREADS)
+        .map(|_| {
+            let path = path.clone();
+
+            thread::spawn(move || {
+                for _ in 0..ROUNDS {
+                    // Each thread opens the lock file on its own, like a separate process would
+                    let lock = loop {
+                        match Mutex::<u32>::init_exclusive(&path) {
+                            Ok(lock) => break lock,
+                            Err(Error::FileLocked) => thread::yield_now(),
+                            Err(err) => panic!("{}", err),
+                        }
+                    };
+
+                    *lock.lock() += 1;
+                    lock.save().unwrap();
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+
+    threads
+        .into_iter()
+        .for_each(|thread| thread.join().unwrap());
+
+    assert_eq!(*Mutex::<u32>::init(&path).unwrap().lock(), THREADS * ROUNDS);
+    let _ = fs::remove_file(&path);
+    let _ = fs::remove_file(PathBuf::from(format!("{}.lock", path.display())));
+    let _ = fs::remove_file(PathBuf::from(format!("{}.sum", path.display())));
+}
@@ -0,0 +1,145 @@
+use jsave::Mutex;
+use std::{collections::HashMap, env, fs, path::PathBuf};
+
+fn tmp_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("jsave-{}-{}", name, std::process::id()))
+}
+
+#[test]
+fn sorted_keys_are_written_in_order() {
+    let path = tmp_path("sorted.json");
+    let data = (0..16)
+        .map(|i| (i.to_string(), i))
+        .collect::<HashMap<_, u32>>();
+    let lock: Mutex<HashMap<String, u32>> = Mutex::init_with(data, &path)
+        .unwrap()
+        .with_sorted_keys(true);
+
+    lock.mark_dirty();
+    lock.save().unwrap();
+    let content = fs::read_to_string(&path).unwrap();
+    let mut keys = (0..16).map(|i| i.to_string()).collect::<Vec<_>>();
+    keys.sort();
+
+    let positions = keys
+        .iter()
+        .map(|key| content.find(&format!("\"{}\"", key)).unwrap())
+        .collect::<Vec<_>>();
+
+    assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+    let _ = fs::remove_file(&path);
+}
+
+#[cfg(feature = "bincode")]
+#[test]
+fn sorted_keys_are_rejected_by_bincode() {
+    let path = tmp_path("sorted.bin");
+    let lock: Mutex<HashMap<String, u32>, jsave::Bincode> =
+        Mutex::init_with(HashMap::new(), &path).unwrap();
+    let lock = lock.with_sorted_keys(true);
+
+    lock.lock().insert("a".to_owned(), 1);
+    let err = lock.save().unwrap_err();
+    assert!(matches!(err.root(), jsave::Error::UnsupportedFormat(_)));
+
+    drop(lock);
+    let lock: Mutex<HashMap<String, u32>, jsave::Bincode> = Mutex::init(&path).unwrap();
+    assert!(lock.lock().is_empty());
+    let _ = fs::remove_file(&path);
+}
+
+#[cfg(feature = "cbor")]
+#[test]
+fn canonical_is_rejected_by_cbor() {
+    let path = tmp_path("canonical.cbor");
+    let lock: Mutex<Vec<u32>, jsave::Cbor> = Mutex::init_with(vec![1], &path).unwrap();
+    let lock = lock.with_canonical(true);
+
+    lock.lock().push(2);
+    let err = lock.save().unwrap_err();
+    assert!(matches!(err.root(), jsave::Error::UnsupportedFormat(_)));
+    let _ = fs::remove_file(&path);
+}
+
+#[cfg(feature = "bincode")]
+#[test]
+fn trailing_newline_is_rejected_by_bincode() {
+    let path = tmp_path("newline.bin");
+    let lock: Mutex<Vec<u32>, jsave::Bincode> = Mutex::init_with(vec![1], &path).unwrap();
+    let lock = lock.with_trailing_newline(true);
+
+    lock.lock().push(2);
+    let err = lock.save().unwrap_err();
+    assert!(matches!(
+        err.root(),
+        jsave::Error::UnsupportedFormat("with_trailing_newline")
+    ));
+
+    drop(lock);
+    let lock: Mutex<Vec<u32>, jsave::Bincode> = Mutex::init(&path).unwrap();
+    assert_eq!(*lock.lock(), [1]);
+    let _ = fs::remove_file(&path);
+}
+
+#[cfg(feature = "msgpack")]
+mod msgpack {
+    use super::tmp_path;
+    use jsave::{MessagePack, Mutex};
+    use serde::{Deserialize, Serialize};
+    use std::{collections::BTreeMap, fs};
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    enum Shape {
+        Point,
+        Circle(f64),
+        Rect { width: u32, height: u32 },
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Data {
+        shapes: BTreeMap<String, BTreeMap<u32, Shape>>,
+        tags: Vec<Option<String>>,
+    }
+
+    #[test]
+    fn message_pack_round_trip() {
+        let path = tmp_path("round-trip.msgpack");
+        let mut shapes = BTreeMap::new();
+        let mut inner = BTreeMap::new();
+        inner.insert(0, Shape::Point);
+        inner.insert(1, Shape::Circle(1.5));
+        inner.insert(
+            2,
+            Shape::Rect {
+                width: 3,
+                height: 4,
+            },
+        );
+        shapes.insert("a".to_owned(), inner);
+        shapes.insert("b".to_owned(), BTreeMap::new());
+
+        let data = Data {
+            shapes,
+            tags: vec![Some("x".to_owned()), None],
+        };
+
+        let lock: Mutex<Data, MessagePack> = Mutex::init_with(data, &path).unwrap();
+        lock.lock().tags.push(None);
+        lock.save().unwrap();
+        drop(lock);
+
+        let loaded: Mutex<Data, MessagePack> = Mutex::init(&path).unwrap();
+        let loaded = loaded.lock();
+        assert_eq!(loaded.shapes["a"][&1], Shape::Circle(1.5));
+        assert_eq!(
+            loaded.shapes["a"][&2],
+            Shape::Rect {
+                width: 3,
+                height: 4
+            }
+        );
+        assert!(loaded.shapes["b"].is_empty());
+        assert_eq!(loaded.tags, [Some("x".to_owned()), None, None]);
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,60 @@
+use jsave::{
+    mutex::{ArcMutexGuard, MappedMutexGuard, MutexGuard},
+    rwlock::{ArcRwLockWriteGuard, MappedRwLockWriteGuard, RwLockWriteGuard},
+    Mutex, RwLock,
+};
+use std::{env, fs, path::PathBuf};
+
+fn tmp_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("jsave-{}-{}.json", name, std::process::id()))
+}
+
+fn assert_sync<T: Sync>() {}
+
+#[test]
+fn mapped_guards_are_sync() {
+    assert_sync::<MappedMutexGuard<'_, u32>>();
+    assert_sync::<MappedRwLockWriteGuard<'_, u32>>();
+}
+
+#[test]
+fn unsized_data_can_be_mapped() {
+    let path = tmp_path("map-unsized");
+    let lock: Box<RwLock<[u32]>> = Box::new(RwLock::init_with([1, 2], &path).unwrap());
+
+    {
+        let mut first = RwLockWriteGuard::map(lock.write(), |data| &mut data[0]);
+        *first = 3;
+        MappedRwLockWriteGuard::save(&first).unwrap();
+    }
+
+    assert_eq!(*RwLock::<Vec<u32>>::init(&path).unwrap().read(), [3, 2]);
+
+    let lock: Box<Mutex<[u32]>> = Box::new(Mutex::init_with([1, 2], &path).unwrap());
+    *MutexGuard::map(lock.lock(), |data| &mut data[1]) = 4;
+    MutexGuard::save(&lock.lock()).unwrap();
+    assert_eq!(*Mutex::<Vec<u32>>::init(&path).unwrap().lock(), [1, 4]);
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn arc_guards_save_while_held() {
+    let path = tmp_path("arc-save");
+
+    let lock = RwLock::<Vec<u32>>::init_with_arc(vec![1], &path).unwrap();
+    let mut guard = lock.write_arc();
+    guard.push(2);
+    ArcRwLockWriteGuard::save(&guard).unwrap();
+    assert!(!ArcRwLockWriteGuard::save_if_changed(&guard).unwrap());
+    drop(guard);
+    assert_eq!(*RwLock::<Vec<u32>>::init(&path).unwrap().read(), [1, 2]);
+
+    let lock = Mutex::<Vec<u32>>::init_with_arc(vec![1], &path).unwrap();
+    let mut guard = lock.lock_arc();
+    guard.push(3);
+    assert!(ArcMutexGuard::save_if_changed(&guard).unwrap());
+    ArcMutexGuard::save(&guard).unwrap();
+    drop(guard);
+    assert_eq!(*Mutex::<Vec<u32>>::init(&path).unwrap().lock(), [1, 3]);
+    let _ = fs::remove_file(&path);
+}
@@ -0,0 +1,17 @@
+use jsave::Mutex;
+use std::io::ErrorKind;
+
+#[test]
+fn memory_lock_has_no_file() {
+    let lock: Mutex<Vec<u32>> = Mutex::init_memory(vec![1]).unwrap();
+    assert!(lock.is_memory());
+    assert!(!lock.path().exists());
+
+    lock.lock().push(2);
+    lock.save().unwrap();
+    lock.reload().unwrap();
+    assert_eq!(*lock.lock(), [1, 2]);
+
+    let err = lock.file_metadata().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Unsupported);
+}
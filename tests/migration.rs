@@ -0,0 +1,13 @@
+use jsave::{Error, Mutex};
+use std::{env, fs};
+
+#[test]
+fn migration_errors_name_the_file() {
+    let path = env::temp_dir().join(format!("jsave-migration-{}.json", std::process::id()));
+    fs::write(&path, "[1]").unwrap();
+
+    let err = Mutex::<Vec<u32>>::init_with_migration(&path, |_| "not a list".into()).unwrap_err();
+    assert!(matches!(&err, Error::Load { path: err_path, .. } if *err_path == path));
+    assert!(matches!(err.root(), Error::Json(_)));
+    let _ = fs::remove_file(&path);
+}
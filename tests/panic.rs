@@ -0,0 +1,67 @@
+use jsave::Mutex;
+use serde::{ser::SerializeSeq, Deserialize, Serialize, Serializer};
+use std::{
+    env, fs,
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
+};
+
+#[derive(Deserialize)]
+#[serde(from = "Vec<u32>")]
+struct Items {
+    items: Vec<u32>,
+    panic_at: Option<usize>,
+}
+
+impl From<Vec<u32>> for Items {
+    fn from(items: Vec<u32>) -> Self {
+        Self {
+            items,
+            panic_at: None,
+        }
+    }
+}
+
+impl Serialize for Items {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.items.len()))?;
+
+        for (i, item) in self.items.iter().enumerate() {
+            if self.panic_at == Some(i) {
+                panic!("serializing item {}", i);
+            }
+
+            seq.serialize_element(item)?;
+        }
+
+        seq.end()
+    }
+}
+
+#[test]
+fn panicking_serialize_removes_the_temporary_file() {
+    let path = env::temp_dir().join(format!("jsave-panic-{}.json", std::process::id()));
+    let mut tmp_path = path.clone().into_os_string();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+
+    let items = Items {
+        items: vec![1, 2],
+        panic_at: None,
+    };
+    let lock: Mutex<Items> = Mutex::init_with(items, &path).unwrap();
+
+    {
+        let mut data = lock.lock();
+        data.items.extend(3..1000);
+        data.panic_at = Some(500);
+    }
+
+    assert!(panic::catch_unwind(AssertUnwindSafe(|| lock.save())).is_err());
+    assert!(!tmp_path.exists());
+    lock.lock().panic_at = None;
+    drop(lock);
+
+    assert_eq!(*Mutex::<Vec<u32>>::init(&path).unwrap().lock(), [1, 2]);
+    let _ = fs::remove_file(&path);
+}
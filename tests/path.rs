@@ -0,0 +1,60 @@
+use jsave::RwLock;
+use std::{env, fs};
+
+#[test]
+fn set_path_and_save_writes_the_new_file() {
+    let dir = env::temp_dir();
+    let old = dir.join(format!("jsave-set-path-old-{}.json", std::process::id()));
+    let new = dir.join(format!("jsave-set-path-new-{}.json", std::process::id()));
+
+    let mut lock: RwLock<Vec<u32>> = RwLock::init_with(vec![1, 2], &old).unwrap();
+    lock.set_path_and_save(&new).unwrap();
+    assert_eq!(lock.path(), new);
+    assert_eq!(*RwLock::<Vec<u32>>::init(&new).unwrap().read(), [1, 2]);
+
+    let _ = fs::remove_file(&old);
+    let _ = fs::remove_file(&new);
+}
+
+#[test]
+fn save_replaces_an_existing_file() {
+    let path = env::temp_dir().join(format!("jsave-replace-{}.json", std::process::id()));
+    fs::write(&path, "[1]").unwrap();
+
+    let lock: RwLock<Vec<u32>> = RwLock::init(&path).unwrap();
+
+    for i in 2..4 {
+        lock.write().push(i);
+        lock.save().unwrap();
+        assert_eq!(
+            serde_json::from_slice::<Vec<u32>>(&fs::read(&path).unwrap()).unwrap(),
+            *lock.read()
+        );
+    }
+
+    let mut tmp_path = path.clone().into_os_string();
+    tmp_path.push(".tmp");
+    assert!(!std::path::Path::new(&tmp_path).exists());
+    assert_eq!(*RwLock::<Vec<u32>>::init(&path).unwrap().read(), [1, 2, 3]);
+    let _ = fs::remove_file(&path);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn temp_dir_on_another_filesystem_is_copied_over() {
+    let temp_dir = std::path::Path::new("/dev/shm");
+
+    if !temp_dir.is_dir() {
+        return;
+    }
+
+    let path = env::temp_dir().join(format!("jsave-temp-dir-{}.json", std::process::id()));
+    let lock: RwLock<Vec<u32>> = RwLock::init_with(vec![1], &path)
+        .unwrap()
+        .with_temp_dir(temp_dir);
+
+    lock.write().push(2);
+    lock.save().unwrap();
+    assert_eq!(*RwLock::<Vec<u32>>::init(&path).unwrap().read(), [1, 2]);
+    let _ = fs::remove_file(&path);
+}
@@ -0,0 +1,46 @@
+use jsave::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::{env, fs, path::PathBuf};
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Default)]
+struct Data {
+    persisted: Vec<u32>,
+    cache: u32,
+}
+
+fn tmp_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("jsave-{}-{}.json", name, std::process::id()))
+}
+
+#[test]
+fn save_after_projection_writes_the_whole_data() {
+    let path = tmp_path("projection-save");
+    let lock: Mutex<Data> = Mutex::init_with(Data::default(), &path).unwrap();
+
+    lock.save_projection(|data| &data.persisted).unwrap();
+    assert!(lock.save().unwrap() > 0);
+    drop(lock);
+
+    let lock: Mutex<Data> = Mutex::init(&path).unwrap();
+    assert_eq!(*lock.lock(), Data::default());
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn init_projection_builds_the_data() {
+    let path = tmp_path("projection-init");
+    let lock: RwLock<Data> = RwLock::init_with(Data::default(), &path).unwrap();
+    lock.write().persisted.push(1);
+    lock.save_projection(|data| &data.persisted).unwrap();
+    drop(lock);
+
+    let lock: RwLock<Data> = RwLock::init_projection(&path, |persisted| Data {
+        persisted,
+        cache: 7,
+    })
+    .unwrap();
+
+    assert_eq!(lock.read().persisted, [1]);
+    assert_eq!(lock.read().cache, 7);
+    let _ = fs::remove_file(&path);
+}
@@ -0,0 +1,30 @@
+use jsave::Mutex;
+use std::{env, fs};
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+struct Account {
+    name: String,
+    password: String,
+}
+
+#[test]
+fn save_if_changed_applies_pre_save() {
+    let path = env::temp_dir().join(format!("jsave-if-changed-{}.json", std::process::id()));
+    let account = Account {
+        name: "a".to_owned(),
+        password: "secret".to_owned(),
+    };
+
+    let lock = Mutex::<Account>::init_with(account, &path)
+        .unwrap()
+        .with_pre_save(|account: &mut Account| account.password.clear());
+
+    assert!(lock.save_if_changed().unwrap());
+    assert!(!fs::read_to_string(&path).unwrap().contains("secret"));
+    assert!(!lock.save_if_changed().unwrap());
+
+    lock.lock().name = "b".to_owned();
+    assert!(lock.save_if_changed().unwrap());
+    assert!(!lock.save_if_changed().unwrap());
+    let _ = fs::remove_file(&path);
+}
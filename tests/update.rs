@@ -0,0 +1,53 @@
+use jsave::{Mutex, RwLock};
+use std::{
+    env, fs,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+};
+
+fn tmp_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("jsave-{}-{}.json", name, std::process::id()))
+}
+
+fn read(path: &Path) -> Vec<u32> {
+    serde_json::from_slice(&fs::read(path).unwrap()).unwrap()
+}
+
+#[test]
+fn with_write_discards_changes_on_panic() {
+    let path = tmp_path("with-write-mutex");
+    let lock: Mutex<Vec<u32>> = Mutex::init_with(vec![1], &path).unwrap();
+
+    let res = panic::catch_unwind(AssertUnwindSafe(|| {
+        lock.with_write(|data| {
+            data.push(2);
+            panic!("interrupted");
+        })
+    }));
+
+    assert!(res.is_err());
+    assert_eq!(*lock.lock(), [1]);
+
+    lock.with_write(|data| data.push(3)).unwrap();
+    assert_eq!(read(&path), [1, 3]);
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn rwlock_with_write_discards_changes_on_panic() {
+    let path = tmp_path("with-write-rwlock");
+    let lock: RwLock<Vec<u32>> = RwLock::init_with(vec![1], &path).unwrap();
+
+    let res = panic::catch_unwind(AssertUnwindSafe(|| {
+        lock.with_write(|data| {
+            data.push(2);
+            panic!("interrupted");
+        })
+    }));
+
+    assert!(res.is_err());
+    lock.mark_dirty();
+    lock.save().unwrap();
+    assert_eq!(read(&path), [1]);
+    let _ = fs::remove_file(&path);
+}